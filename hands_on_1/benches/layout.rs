@@ -0,0 +1,60 @@
+//! Compares the memory use and the `sum()` throughput of `Tree` and `CompactTree`.
+//! Both sums visit the nodes recursively in the same order, so only the layout differs.
+//!
+//! Run with `cargo bench --bench layout -- <nodes>`, 10^6 nodes by default.
+
+use hands_on_1::compact::CompactTree;
+use hands_on_1::random::{random_tree, Rng};
+use hands_on_1::Node;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 10;
+
+/// Average time of a call of `f`, over `ROUNDS` calls.
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(f());
+    }
+
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let n: usize = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(1_000_000);
+
+    let mut rng = Rng::new(2024);
+    let tree = random_tree(&mut rng, n, -1000..=1000);
+    let compact = CompactTree::from(&tree);
+
+    let tree_bytes = n * std::mem::size_of::<Node>();
+    let compact_bytes = n * (std::mem::size_of::<i32>() + 2 * std::mem::size_of::<u32>());
+
+    println!("nodes: {}", n);
+    for (name, bytes) in [("Tree", tree_bytes), ("CompactTree", compact_bytes)] {
+        println!(
+            "memory  {:<12} {:>10} bytes  {:>8} bytes/node",
+            name,
+            bytes,
+            bytes / n
+        );
+    }
+
+    assert_eq!(tree.sum(), compact.sum());
+
+    for (name, elapsed) in [
+        ("Tree", time(|| tree.sum())),
+        ("CompactTree", time(|| compact.sum())),
+    ] {
+        println!(
+            "sum()   {:<12} {:>10.3} ms  {:>8.1} Mnodes/s",
+            name,
+            elapsed.as_secs_f64() * 1e3,
+            n as f64 / elapsed.as_secs_f64() / 1e6
+        );
+    }
+}
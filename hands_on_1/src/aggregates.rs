@@ -0,0 +1,268 @@
+use crate::{max_path_step, Tree};
+
+/// Per-subtree results stored by a `Tree` in cached-aggregate mode.
+///
+/// Every vector is indexed by node id. `path[id]` holds the same couple
+/// returned by `rec_max_path_sum` for the subtree rooted at `id`.
+pub(crate) struct Aggregates {
+    sum: Vec<i32>,
+    path: Vec<(Option<i32>, Option<i32>)>,
+}
+
+impl Aggregates {
+    pub(crate) fn sum(&self, id: usize) -> i32 {
+        self.sum[id]
+    }
+
+    pub(crate) fn path(&self, id: usize) -> (Option<i32>, Option<i32>) {
+        self.path[id]
+    }
+}
+
+impl Tree {
+    /// Enables the cached-aggregate mode. The tree computes once, in O(n), the sum
+    /// and the max path sums of every subtree and keeps them up to date on
+    /// `add_node` and `set_key`, so that `sum` and `max_path_sum` run in O(1).
+    ///
+    /// Every update then costs O(h), where h is the depth of the changed node.
+    pub fn enable_cached_aggregates(&mut self) {
+        if self.cache.is_some() {
+            return;
+        }
+
+        let n = self.nodes.len();
+        let mut cache = Aggregates {
+            sum: vec![0; n],
+            path: vec![(None, None); n],
+        };
+
+        for id in self.postorder() {
+            let (sum, path) = self.aggregate(&cache, id);
+            cache.sum[id] = sum;
+            cache.path[id] = path;
+        }
+
+        // The repairs walk up the tree, so they need the parent links
+        self.parents();
+        self.cache = Some(cache);
+    }
+
+    /// Disables the cached-aggregate mode and frees the cached values.
+    pub fn disable_cached_aggregates(&mut self) {
+        self.cache = None;
+    }
+
+    /// Returns `true` iff the tree is in cached-aggregate mode.
+    pub fn has_cached_aggregates(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// Sets the key of the node `id` to `key`.
+    /// In cached-aggregate mode only the ancestors of the node are repaired.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist.
+    pub fn set_key(&mut self, id: usize, key: i32) {
+        assert!(id < self.nodes.len(), "Node id does not exist");
+
+        self.nodes[id].key = key;
+
+        if self.cache.is_some() {
+            self.repair(id);
+        }
+    }
+
+    /// Stores in the cache the new leaf just pushed as the last node of the arena,
+    /// then repairs its ancestors.
+    pub(crate) fn cache_push_leaf(&mut self, parent_id: usize, key: i32) {
+        if let Some(cache) = &mut self.cache {
+            cache.sum.push(key);
+            cache.path.push((Some(key), None));
+        }
+
+        self.repair(parent_id);
+    }
+
+    /// Recomputes the cached values of `id` and of its ancestors from the cached
+    /// values of their children. It stops as soon as a node is left unchanged,
+    /// since the ancestors only depend on it through its cached values.
+    pub(crate) fn repair(&mut self, id: usize) {
+        let Some(mut cache) = self.cache.take() else {
+            return;
+        };
+
+        let mut current = Some(id);
+        while let Some(id) = current {
+            let (sum, path) = self.aggregate(&cache, id);
+
+            if cache.sum[id] == sum && cache.path[id] == path {
+                break;
+            }
+
+            cache.sum[id] = sum;
+            cache.path[id] = path;
+            current = self.parents()[id];
+        }
+
+        self.cache = Some(cache);
+    }
+
+    /// Recomputes the cached values of `id` alone, even if its children have been
+    /// replaced, so that the old cached values can't be compared with the new ones.
+    pub(crate) fn refresh(&mut self, id: usize) {
+        let Some(mut cache) = self.cache.take() else {
+            return;
+        };
+
+        let (sum, path) = self.aggregate(&cache, id);
+        cache.sum[id] = sum;
+        cache.path[id] = path;
+
+        self.cache = Some(cache);
+    }
+
+    /// Computes the aggregates of `id` from the cached values of its children.
+    fn aggregate(&self, cache: &Aggregates, id: usize) -> (i32, (Option<i32>, Option<i32>)) {
+        let node = &self.nodes[id];
+
+        let mut sum = node.key;
+        let mut left = (None, None);
+        let mut right = (None, None);
+
+        if let Some(id_left) = node.id_left {
+            sum += cache.sum[id_left];
+            left = cache.path[id_left];
+        }
+        if let Some(id_right) = node.id_right {
+            sum += cache.sum[id_right];
+            right = cache.path[id_right];
+        }
+
+        (sum, max_path_step(node.key, left, right))
+    }
+}
+
+#[cfg(test)]
+mod aggregates_tests {
+    use super::*;
+
+    /// Checks the cached answers against a full recompute of the tree.
+    fn assert_matches_recompute(tree: &Tree) {
+        assert_eq!(tree.sum(), tree.rec_sum(Some(tree.root)));
+        assert_eq!(tree.max_path_sum(), tree.rec_max_path_sum(Some(tree.root)).1);
+    }
+
+    #[test]
+    fn set_key_without_cache() {
+        let mut tree = Tree::with_root(10);
+
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, 5, false); // id 2
+
+        tree.set_key(2, 15);
+
+        assert!(!tree.has_cached_aggregates());
+        assert_eq!(tree.sum(), 30);
+        assert_eq!(tree.max_path_sum(), Some(30));
+    }
+
+    #[test]
+    fn set_key_with_cache() {
+        let mut tree = Tree::with_root(-20);
+
+        //       -20
+        //     /    \
+        //   -10     9
+        //         /  \
+        //        10   5
+        tree.add_node(0, -10, true); // id 1
+        tree.add_node(0, 9, false); // id 2
+        tree.add_node(2, 10, true); // id 3
+        tree.add_node(2, 5, false); // id 4
+
+        tree.enable_cached_aggregates();
+        assert_eq!(tree.sum(), -6);
+        assert_eq!(tree.max_path_sum(), Some(24));
+
+        tree.set_key(1, 100);
+        assert_eq!(tree.sum(), 104);
+        assert_eq!(tree.max_path_sum(), Some(99));
+        assert_matches_recompute(&tree);
+
+        tree.set_key(4, 50);
+        assert_eq!(tree.max_path_sum(), Some(139));
+        assert_matches_recompute(&tree);
+    }
+
+    #[test]
+    fn add_node_with_cache() {
+        let mut tree = Tree::with_root(10);
+        tree.enable_cached_aggregates();
+
+        //     10
+        //    /
+        //   5
+        tree.add_node(0, 5, true); // id 1
+        assert_eq!(tree.max_path_sum(), None);
+        assert_matches_recompute(&tree);
+
+        //     10
+        //    /  \
+        //   5    7
+        tree.add_node(0, 7, false); // id 2
+        assert_eq!(tree.max_path_sum(), Some(22));
+        assert_matches_recompute(&tree);
+
+        //     10
+        //    /  \
+        //   5    7
+        //  /
+        // -3
+        tree.add_node(1, -3, true); // id 3
+        assert_eq!(tree.sum(), 19);
+        assert_eq!(tree.max_path_sum(), Some(19));
+        assert_matches_recompute(&tree);
+    }
+
+    #[test]
+    fn disable_cache() {
+        let mut tree = Tree::with_root(1);
+        tree.add_node(0, 2, true); // id 1
+
+        tree.enable_cached_aggregates();
+        tree.disable_cached_aggregates();
+        tree.set_key(1, 5);
+
+        assert!(!tree.has_cached_aggregates());
+        assert_eq!(tree.sum(), 6);
+    }
+
+    #[test]
+    fn many_updates() {
+        let mut tree = Tree::with_root(0);
+        tree.enable_cached_aggregates();
+
+        // Small linear congruential generator, enough to shuffle the updates
+        let mut state: u64 = 42;
+        let mut next = move |bound: u64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % bound
+        };
+
+        for _ in 0..2000 {
+            let n = tree.nodes.len() as u64;
+            let id = next(n) as usize;
+            let key = next(201) as i32 - 100;
+
+            if next(3) == 0 {
+                tree.set_key(id, key);
+            } else if tree.nodes[id].id_left.is_none() {
+                tree.add_node(id, key, true);
+            } else if tree.nodes[id].id_right.is_none() {
+                tree.add_node(id, key, false);
+            }
+
+            assert_matches_recompute(&tree);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{max_path_step, Tree};
+
+/// Marks a missing child in `CompactTree`, in place of `Option::None`.
+const NONE: u32 = u32::MAX;
+
+/// A binary tree with the same API of `Tree`, stored as a struct of arrays.
+///
+/// Keys and children live in three separate vectors indexed by node id, and
+/// children are `u32` ids with a sentinel for missing ones. Every node costs
+/// 12 bytes instead of the 40 bytes of a `Node`.
+pub struct CompactTree {
+    keys: Vec<i32>,
+    left: Vec<u32>,
+    right: Vec<u32>,
+    root: u32,
+}
+
+impl CompactTree {
+    pub fn with_root(key: i32) -> Self {
+        Self {
+            keys: vec![key],
+            left: vec![NONE],
+            right: vec![NONE],
+            root: 0,
+        }
+    }
+
+    /// Adds a child to the node with `parent_id` and returns the id of the new node.
+    /// The new node has the specified `key`. The new node is the left child of the
+    /// node `parent_id` iff `is_left` is `true`, the right child otherwise.
+    ///
+    /// # Panics
+    /// Panics if the `parent_id` does not exist, if the node `parent_id` has
+    /// the child already set, or if the tree already has `u32::MAX` nodes.
+    pub fn add_node(&mut self, parent_id: usize, key: i32, is_left: bool) -> usize {
+        assert!(
+            parent_id < self.keys.len(),
+            "Parent node id does not exist"
+        );
+        assert!(self.keys.len() < NONE as usize, "The tree is full");
+
+        let child = if is_left {
+            &mut self.left[parent_id]
+        } else {
+            &mut self.right[parent_id]
+        };
+        assert!(
+            *child == NONE,
+            "Parent node has the {} child already set",
+            if is_left { "left" } else { "right" }
+        );
+
+        let child_id = self.keys.len();
+        *child = child_id as u32;
+
+        self.keys.push(key);
+        self.left.push(NONE);
+        self.right.push(NONE);
+
+        child_id
+    }
+
+    /// Sets the key of the node `id` to `key`.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist.
+    pub fn set_key(&mut self, id: usize, key: i32) {
+        assert!(id < self.keys.len(), "Node id does not exist");
+        self.keys[id] = key;
+    }
+
+    /// Returns the number of nodes in the tree.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Always returns `false`, a tree has at least the root.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns the sum of all the keys in the tree
+    pub fn sum(&self) -> i32 {
+        self.rec_sum(self.root)
+    }
+
+    /// A private recursive function that computes the sum of
+    /// nodes in the subtree rooted at `id`, in the same order of `Tree::sum`.
+    fn rec_sum(&self, id: u32) -> i32 {
+        if id == NONE {
+            return 0;
+        }
+
+        let sum_left = self.rec_sum(self.left[id as usize]);
+        let sum_right = self.rec_sum(self.right[id as usize]);
+
+        sum_left + sum_right + self.keys[id as usize]
+    }
+
+    /// Checks if the tree is a Binary Search Tree, with an iterative in-order
+    /// visit: the keys must come out in non-decreasing order.
+    pub fn is_bst(&self) -> bool {
+        let mut stack: Vec<u32> = Vec::new();
+        let mut current = self.root;
+        let mut previous: Option<i32> = None;
+
+        loop {
+            while current != NONE {
+                stack.push(current);
+                current = self.left[current as usize];
+            }
+
+            let Some(id) = stack.pop() else {
+                return true;
+            };
+
+            let key = self.keys[id as usize];
+            if previous.is_some_and(|previous| previous > key) {
+                return false;
+            }
+
+            previous = Some(key);
+            current = self.right[id as usize];
+        }
+    }
+
+    /// Returns the maximum path sum between two leaves in the tree.
+    /// If no path is found then it returns None.
+    pub fn max_path_sum(&self) -> Option<i32> {
+        // Results of `max_path_step` of the visited nodes
+        let mut results: Vec<(Option<i32>, Option<i32>)> = vec![(None, None); self.keys.len()];
+
+        for id in self.postorder() {
+            let child = |child: u32| {
+                if child == NONE {
+                    (None, None)
+                } else {
+                    results[child as usize]
+                }
+            };
+
+            let (left, right) = (child(self.left[id]), child(self.right[id]));
+            results[id] = max_path_step(self.keys[id], left, right);
+        }
+
+        results[self.root as usize].1
+    }
+
+    /// Returns the ids of the nodes in post-order, with an explicit stack.
+    fn postorder(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = Vec::with_capacity(self.keys.len());
+        let mut stack: Vec<u32> = vec![self.root];
+
+        // Visits node, right, left and then reverses the result
+        while let Some(id) = stack.pop() {
+            order.push(id as usize);
+
+            for child in [self.left[id as usize], self.right[id as usize]] {
+                if child != NONE {
+                    stack.push(child);
+                }
+            }
+        }
+
+        order.reverse();
+        order
+    }
+}
+
+/// Copies a `Tree` keeping the same node ids.
+///
+/// # Panics
+/// Panics if the tree has `u32::MAX` nodes or more.
+impl From<&Tree> for CompactTree {
+    fn from(tree: &Tree) -> Self {
+        assert!(tree.nodes.len() < NONE as usize, "The tree is too big");
+
+        let id = |child: Option<usize>| child.map_or(NONE, |child| child as u32);
+
+        Self {
+            keys: tree.nodes.iter().map(|node| node.key).collect(),
+            left: tree.nodes.iter().map(|node| id(node.id_left)).collect(),
+            right: tree.nodes.iter().map(|node| id(node.id_right)).collect(),
+            root: tree.root as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod compact_tests {
+    use super::*;
+    use crate::random::{random_bst, random_chain, random_tree, Rng};
+
+    #[test]
+    fn same_api() {
+        let mut tree = CompactTree::with_root(-20);
+
+        //       -20
+        //     /    \
+        //   -10     9
+        //         /  \
+        //        10   5
+        tree.add_node(0, -10, true); // id 1
+        tree.add_node(0, 9, false); // id 2
+        tree.add_node(2, 10, true); // id 3
+        tree.add_node(2, 5, false); // id 4
+
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.sum(), -6);
+        assert_eq!(tree.max_path_sum(), Some(24));
+        assert!(!tree.is_bst());
+
+        tree.set_key(0, 0);
+        tree.set_key(2, 7);
+        tree.set_key(3, 6);
+        tree.set_key(4, 8);
+        assert!(tree.is_bst());
+    }
+
+    #[test]
+    #[should_panic(expected = "Parent node has the left child already set")]
+    fn child_already_set() {
+        let mut tree = CompactTree::with_root(1);
+
+        tree.add_node(0, 2, true);
+        tree.add_node(0, 3, true);
+    }
+
+    #[test]
+    fn same_results_as_tree() {
+        let mut rng = Rng::new(31);
+
+        for _ in 0..1000 {
+            let n = 1 + rng.below(50);
+            let tree = match rng.below(3) {
+                0 => random_tree(&mut rng, n, -50..=50),
+                1 => random_bst(&mut rng, n, -50..=50),
+                _ => random_chain(&mut rng, n, -50..=50),
+            };
+            let compact = CompactTree::from(&tree);
+
+            assert_eq!(compact.len(), n);
+            assert_eq!(compact.sum(), tree.sum());
+            assert_eq!(compact.is_bst(), tree.is_bst());
+            assert_eq!(compact.max_path_sum(), tree.max_path_sum());
+        }
+    }
+}
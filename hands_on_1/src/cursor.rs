@@ -0,0 +1,279 @@
+use crate::Tree;
+
+/// A read-only cursor on a node of a `Tree`, to navigate it from outside.
+/// The first move up the tree builds its parent links in O(n), see `Tree::parents`.
+#[derive(Clone, Copy)]
+pub struct NodeRef<'a> {
+    tree: &'a Tree,
+    id: usize,
+}
+
+impl<'a> NodeRef<'a> {
+    /// Returns the id of the node.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn key(&self) -> i32 {
+        self.tree.nodes[self.id].key
+    }
+
+    pub fn left(&self) -> Option<NodeRef<'a>> {
+        self.to(self.tree.nodes[self.id].id_left)
+    }
+
+    pub fn right(&self) -> Option<NodeRef<'a>> {
+        self.to(self.tree.nodes[self.id].id_right)
+    }
+
+    /// Returns the parent of the node, `None` for the root.
+    pub fn parent(&self) -> Option<NodeRef<'a>> {
+        self.to(self.tree.parents()[self.id])
+    }
+
+    /// Returns `true` iff the node has no children.
+    pub fn is_leaf(&self) -> bool {
+        let node = &self.tree.nodes[self.id];
+        node.id_left.is_none() && node.id_right.is_none()
+    }
+
+    /// Returns `true` iff the node is the root of the tree.
+    pub fn is_root(&self) -> bool {
+        self.id == self.tree.root
+    }
+
+    /// Returns the number of edges between the node and the root. O(depth).
+    pub fn depth(&self) -> usize {
+        let parents = self.tree.parents();
+        let mut depth = 0;
+        let mut current = parents[self.id];
+
+        while let Some(id) = current {
+            depth += 1;
+            current = parents[id];
+        }
+
+        depth
+    }
+
+    fn to(&self, id: Option<usize>) -> Option<NodeRef<'a>> {
+        id.map(|id| NodeRef {
+            tree: self.tree,
+            id,
+        })
+    }
+}
+
+/// A cursor on a node of a `Tree` that can move around and edit the tree in place.
+/// Edits go through the methods of `Tree`, so parent links and cached
+/// aggregates stay up to date.
+pub struct NodeMut<'a> {
+    tree: &'a mut Tree,
+    id: usize,
+}
+
+impl NodeMut<'_> {
+    /// Returns the id of the node under the cursor.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn key(&self) -> i32 {
+        self.tree.nodes[self.id].key
+    }
+
+    /// Returns a read-only cursor on the same node.
+    pub fn as_ref(&self) -> NodeRef<'_> {
+        NodeRef {
+            tree: self.tree,
+            id: self.id,
+        }
+    }
+
+    pub fn set_key(&mut self, key: i32) {
+        self.tree.set_key(self.id, key);
+    }
+
+    /// Adds a left child with the specified `key` and returns its id.
+    ///
+    /// # Panics
+    /// Panics if the node has the left child already set.
+    pub fn add_left(&mut self, key: i32) -> usize {
+        self.tree.add_node(self.id, key, true)
+    }
+
+    /// Adds a right child with the specified `key` and returns its id.
+    ///
+    /// # Panics
+    /// Panics if the node has the right child already set.
+    pub fn add_right(&mut self, key: i32) -> usize {
+        self.tree.add_node(self.id, key, false)
+    }
+
+    /// Moves the cursor to the left child. Returns `false`, without moving,
+    /// if there is no left child.
+    pub fn move_left(&mut self) -> bool {
+        self.move_to(self.tree.nodes[self.id].id_left)
+    }
+
+    /// Moves the cursor to the right child. Returns `false`, without moving,
+    /// if there is no right child.
+    pub fn move_right(&mut self) -> bool {
+        self.move_to(self.tree.nodes[self.id].id_right)
+    }
+
+    /// Moves the cursor to the parent. Returns `false`, without moving,
+    /// if the node is the root.
+    pub fn move_parent(&mut self) -> bool {
+        self.move_to(self.tree.parents()[self.id])
+    }
+
+    /// Swaps the children of the node, see `Tree::swap_children`.
+    pub fn swap_children(&mut self) {
+        self.tree.swap_children(self.id);
+    }
+
+    /// Rotates left the subtree rooted at the node, see `Tree::rotate_left`.
+    /// The cursor stays on the same node, which becomes a left child.
+    ///
+    /// # Panics
+    /// Panics if the node has no right child.
+    pub fn rotate_left(&mut self) {
+        self.tree.rotate_left(self.id);
+    }
+
+    /// Rotates right the subtree rooted at the node, see `Tree::rotate_right`.
+    /// The cursor stays on the same node, which becomes a right child.
+    ///
+    /// # Panics
+    /// Panics if the node has no left child.
+    pub fn rotate_right(&mut self) {
+        self.tree.rotate_right(self.id);
+    }
+
+    fn move_to(&mut self, id: Option<usize>) -> bool {
+        match id {
+            Some(id) => {
+                self.id = id;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Tree {
+    /// Returns a read-only cursor on the node `id`, or `None` if it doesn't exist.
+    pub fn node(&self, id: usize) -> Option<NodeRef<'_>> {
+        (id < self.nodes.len()).then_some(NodeRef { tree: self, id })
+    }
+
+    /// Returns a read-only cursor on the root.
+    pub fn root_node(&self) -> NodeRef<'_> {
+        NodeRef {
+            tree: self,
+            id: self.root,
+        }
+    }
+
+    /// Returns a cursor to edit the tree starting from the node `id`,
+    /// or `None` if it doesn't exist.
+    pub fn node_mut(&mut self, id: usize) -> Option<NodeMut<'_>> {
+        (id < self.nodes.len()).then_some(NodeMut { tree: self, id })
+    }
+
+    /// Returns a cursor to edit the tree starting from the root.
+    pub fn root_node_mut(&mut self) -> NodeMut<'_> {
+        let id = self.root;
+        NodeMut { tree: self, id }
+    }
+}
+
+#[cfg(test)]
+mod cursor_tests {
+    use super::*;
+
+    /// Returns the tree
+    ///
+    ///       20
+    ///     /    \
+    ///   10      21
+    ///  /  \
+    /// 8   15
+    fn sample_tree() -> Tree {
+        let mut tree = Tree::with_root(20);
+
+        tree.add_node(0, 10, true); // id 1
+        tree.add_node(0, 21, false); // id 2
+        tree.add_node(1, 8, true); // id 3
+        tree.add_node(1, 15, false); // id 4
+
+        tree
+    }
+
+    #[test]
+    fn navigation() {
+        let tree = sample_tree();
+        let root = tree.root_node();
+
+        assert!(root.is_root());
+        assert_eq!(root.key(), 20);
+        assert_eq!(root.depth(), 0);
+        assert!(root.parent().is_none());
+
+        let node = root.left().unwrap().right().unwrap();
+        assert_eq!(node.id(), 4);
+        assert_eq!(node.key(), 15);
+        assert_eq!(node.depth(), 2);
+        assert!(node.is_leaf());
+        assert!(node.left().is_none());
+        assert_eq!(node.parent().unwrap().key(), 10);
+        assert_eq!(node.parent().unwrap().parent().unwrap().id(), 0);
+
+        assert!(!tree.node(1).unwrap().is_leaf());
+        assert!(tree.node(5).is_none());
+    }
+
+    #[test]
+    fn edits() {
+        let mut tree = sample_tree();
+        tree.enable_cached_aggregates();
+
+        let mut cursor = tree.root_node_mut();
+        assert!(cursor.move_right());
+        assert!(!cursor.move_right());
+        cursor.set_key(30);
+        let id = cursor.add_left(25);
+        assert!(cursor.move_left());
+        assert_eq!(cursor.id(), id);
+        assert_eq!(cursor.as_ref().depth(), 2);
+
+        assert!(cursor.move_parent());
+        assert!(cursor.move_parent());
+        assert!(!cursor.move_parent());
+        assert_eq!(cursor.key(), 20);
+
+        // The cursor stays on 20, which goes down under 10
+        cursor.rotate_right();
+        assert_eq!(cursor.as_ref().depth(), 1);
+        assert_eq!(cursor.as_ref().parent().unwrap().key(), 10);
+
+        assert_eq!(tree.root_node().key(), 10);
+        assert!(tree.is_bst());
+        assert_eq!(tree.sum(), 108);
+        assert_eq!(tree.sum(), tree.rec_sum(Some(tree.root)));
+        assert_eq!(tree.max_path_sum(), tree.rec_max_path_sum(Some(tree.root)).1);
+    }
+
+    #[test]
+    fn swap_through_cursor() {
+        let mut tree = sample_tree();
+
+        tree.node_mut(1).unwrap().swap_children();
+
+        let node = tree.node(1).unwrap();
+        assert_eq!(node.left().unwrap().key(), 15);
+        assert_eq!(node.right().unwrap().key(), 8);
+        assert!(!tree.is_bst());
+    }
+}
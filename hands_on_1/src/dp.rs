@@ -0,0 +1,170 @@
+use crate::Tree;
+
+/// Best values for the subtree rooted at a node: (node chosen, node not chosen)
+type Choice = (i64, i64);
+
+impl Tree {
+    /// Returns the maximum-weight independent set of the tree, where the weight of
+    /// a node is its key: the maximum sum of the keys of a set of nodes with no two
+    /// of them adjacent, and the ids of such a set in increasing order.
+    /// The empty set is allowed, so nodes with negative keys are never chosen.
+    ///
+    /// It runs in O(n) time, without recursion.
+    pub fn max_independent_set(&self) -> (i64, Vec<usize>) {
+        let order = self.postorder();
+        let mut best: Vec<Choice> = vec![(0, 0); self.nodes.len()];
+
+        for &id in &order {
+            let node = &self.nodes[id];
+            let mut chosen = node.key as i64;
+            let mut skipped = 0;
+
+            for child in [node.id_left, node.id_right].into_iter().flatten() {
+                chosen += best[child].1;
+                skipped += best[child].0.max(best[child].1);
+            }
+
+            best[id] = (chosen, skipped);
+        }
+
+        // A node can be chosen only if its parent is not, then it's chosen if it's better
+        let parents = self.parents();
+        let mut chosen = vec![false; self.nodes.len()];
+        for &id in order.iter().rev() {
+            let parent_chosen = parents[id].is_some_and(|parent| chosen[parent]);
+            chosen[id] = !parent_chosen && best[id].0 > best[id].1;
+        }
+
+        let (take, skip) = best[self.root];
+        (take.max(skip), chosen_ids(&chosen))
+    }
+
+    /// Returns the minimum-weight vertex cover of the tree, where the weight of
+    /// a node is its key: the minimum sum of the keys of a set of nodes that contains
+    /// at least one end of every edge, and the ids of such a set in increasing order.
+    /// Nodes with negative keys are always chosen.
+    ///
+    /// It runs in O(n) time, without recursion.
+    pub fn min_vertex_cover(&self) -> (i64, Vec<usize>) {
+        let order = self.postorder();
+        let mut best: Vec<Choice> = vec![(0, 0); self.nodes.len()];
+
+        for &id in &order {
+            let node = &self.nodes[id];
+            let mut chosen = node.key as i64;
+            let mut skipped = 0;
+
+            // If the node is not in the cover, its children must be
+            for child in [node.id_left, node.id_right].into_iter().flatten() {
+                chosen += best[child].0.min(best[child].1);
+                skipped += best[child].0;
+            }
+
+            best[id] = (chosen, skipped);
+        }
+
+        // A node must be chosen if its parent is not, otherwise it's chosen if it's better
+        let parents = self.parents();
+        let mut chosen = vec![false; self.nodes.len()];
+        for &id in order.iter().rev() {
+            let parent_chosen = parents[id].is_none_or(|parent| chosen[parent]);
+            chosen[id] = !parent_chosen || best[id].0 < best[id].1;
+        }
+
+        let (take, skip) = best[self.root];
+        (take.min(skip), chosen_ids(&chosen))
+    }
+}
+
+fn chosen_ids(chosen: &[bool]) -> Vec<usize> {
+    (0..chosen.len()).filter(|&id| chosen[id]).collect()
+}
+
+#[cfg(test)]
+mod dp_tests {
+    use super::*;
+    use crate::oracle;
+    use crate::random::{random_chain, random_tree, Rng};
+
+    fn key_sum(tree: &Tree, ids: &[usize]) -> i64 {
+        ids.iter().map(|&id| tree.nodes[id].key as i64).sum()
+    }
+
+    /// Returns the tree
+    ///
+    ///        1
+    ///      /   \
+    ///     8     2
+    ///    / \     \
+    ///   2   3     9
+    fn sample_tree() -> Tree {
+        let mut tree = Tree::with_root(1);
+
+        tree.add_node(0, 8, true); // id 1
+        tree.add_node(0, 2, false); // id 2
+        tree.add_node(1, 2, true); // id 3
+        tree.add_node(1, 3, false); // id 4
+        tree.add_node(2, 9, false); // id 5
+
+        tree
+    }
+
+    #[test]
+    fn sample() {
+        let tree = sample_tree();
+
+        assert_eq!(tree.max_independent_set(), (17, vec![1, 5]));
+        assert_eq!(tree.min_vertex_cover(), (8, vec![0, 2, 3, 4]));
+    }
+
+    #[test]
+    fn single_node() {
+        assert_eq!(Tree::with_root(5).max_independent_set(), (5, vec![0]));
+        assert_eq!(Tree::with_root(-5).max_independent_set(), (0, vec![]));
+        assert_eq!(Tree::with_root(5).min_vertex_cover(), (0, vec![]));
+        assert_eq!(Tree::with_root(-5).min_vertex_cover(), (-5, vec![0]));
+    }
+
+    #[test]
+    fn random_trees() {
+        let mut rng = Rng::new(38);
+
+        for _ in 0..1000 {
+            let n = 1 + rng.below(12);
+            let tree = if rng.coin() {
+                random_tree(&mut rng, n, -20..=50)
+            } else {
+                random_chain(&mut rng, n, -20..=50)
+            };
+            let parents = oracle::parents(&tree);
+
+            let (value, ids) = tree.max_independent_set();
+            assert_eq!(value, oracle::max_independent_set(&tree));
+            assert_eq!(key_sum(&tree, &ids), value);
+            assert!(ids
+                .iter()
+                .all(|&id| parents[id].is_none_or(|parent| !ids.contains(&parent))));
+
+            let (value, ids) = tree.min_vertex_cover();
+            assert_eq!(value, oracle::min_vertex_cover(&tree));
+            assert_eq!(key_sum(&tree, &ids), value);
+            assert!((0..n)
+                .all(|id| parents[id]
+                    .is_none_or(|parent| ids.contains(&id) || ids.contains(&parent))));
+        }
+    }
+
+    #[test]
+    fn deep_chain() {
+        let mut rng = Rng::new(39);
+
+        // Deep enough to overflow the stack with recursion
+        let tree = random_chain(&mut rng, 1 << 20, 1..=1);
+        let (value, ids) = tree.max_independent_set();
+        assert_eq!(value, 1 << 19);
+        assert_eq!(ids.len(), 1 << 19);
+
+        let (value, _) = tree.min_vertex_cover();
+        assert_eq!(value, 1 << 19);
+    }
+}
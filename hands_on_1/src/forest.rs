@@ -0,0 +1,432 @@
+use crate::Tree;
+use std::collections::VecDeque;
+
+/// A node of an n-ary arena, stored as first-child/next-sibling.
+/// `last_child` is only kept to append children in O(1).
+pub struct NaryNode {
+    key: i32,
+    first_child: Option<usize>,
+    last_child: Option<usize>,
+    next_sibling: Option<usize>,
+}
+
+impl NaryNode {
+    fn new(key: i32) -> Self {
+        Self {
+            key,
+            first_child: None,
+            last_child: None,
+            next_sibling: None,
+        }
+    }
+}
+
+/// A collection of rooted n-ary trees stored in a single arena.
+/// Node ids are assigned in insertion order, starting from 0.
+#[derive(Default)]
+pub struct Forest {
+    nodes: Vec<NaryNode>,
+    roots: Vec<usize>,
+}
+
+impl Forest {
+    /// Returns an empty forest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new tree made of a single node with the specified `key`
+    /// and returns the id of the new node.
+    pub fn add_root(&mut self, key: i32) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(NaryNode::new(key));
+        self.roots.push(id);
+
+        id
+    }
+
+    /// Adds a child with the specified `key` after the other children of the node
+    /// with `parent_id` and returns the id of the new node.
+    ///
+    /// # Panics
+    /// Panics if the `parent_id` does not exist.
+    pub fn add_child(&mut self, parent_id: usize, key: i32) -> usize {
+        assert!(
+            parent_id < self.nodes.len(),
+            "Parent node id does not exist"
+        );
+
+        let child_id = self.nodes.len();
+        self.nodes.push(NaryNode::new(key));
+
+        match self.nodes[parent_id].last_child {
+            Some(last) => self.nodes[last].next_sibling = Some(child_id),
+            None => self.nodes[parent_id].first_child = Some(child_id),
+        }
+        self.nodes[parent_id].last_child = Some(child_id);
+
+        child_id
+    }
+
+    /// Returns the number of nodes in the forest.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` iff the forest has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the ids of the roots, in insertion order.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// Returns the key of the node `id`.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist.
+    pub fn key(&self, id: usize) -> i32 {
+        assert!(id < self.nodes.len(), "Node id does not exist");
+        self.nodes[id].key
+    }
+
+    /// Returns an iterator over the ids of the children of the node `id`, in insertion order.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist.
+    pub fn children(&self, id: usize) -> Children<'_> {
+        assert!(id < self.nodes.len(), "Node id does not exist");
+
+        Children {
+            nodes: &self.nodes,
+            next: self.nodes[id].first_child,
+        }
+    }
+
+    /// Returns the sum of all the keys in the forest
+    pub fn sum(&self) -> i32 {
+        self.nodes.iter().map(|node| node.key).sum()
+    }
+
+    /// Returns the number of levels of the tallest tree: 0 for an empty
+    /// forest, 1 for a forest of single nodes.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut level: Vec<usize> = self.roots.clone();
+
+        while !level.is_empty() {
+            height += 1;
+            level = level.iter().flat_map(|&id| self.children(id)).collect();
+        }
+
+        height
+    }
+
+    /// Returns an iterator over the ids of the nodes in pre-order,
+    /// visiting the trees in the order of their roots.
+    pub fn preorder(&self) -> Preorder<'_> {
+        Preorder {
+            forest: self,
+            stack: self.roots.iter().rev().copied().collect(),
+        }
+    }
+
+    /// Returns an iterator over the ids of the nodes level by level.
+    /// The roots come first, then all their children and so on.
+    pub fn level_order(&self) -> LevelOrder<'_> {
+        LevelOrder {
+            forest: self,
+            queue: self.roots.iter().copied().collect(),
+        }
+    }
+
+    /// Copies the subtree of `tree` rooted at `id` below `parent_id`,
+    /// or as a new root if `parent_id` is `None`.
+    fn append_binary(&mut self, tree: &Tree, id: usize, parent_id: Option<usize>) {
+        let mut stack: Vec<(usize, Option<usize>)> = vec![(id, parent_id)];
+
+        while let Some((id, parent_id)) = stack.pop() {
+            let node = &tree.nodes[id];
+            let new_id = match parent_id {
+                Some(parent_id) => self.add_child(parent_id, node.key),
+                None => self.add_root(node.key),
+            };
+
+            // The right child is pushed first so that the left one is added first
+            if let Some(id_right) = node.id_right {
+                stack.push((id_right, Some(new_id)));
+            }
+            if let Some(id_left) = node.id_left {
+                stack.push((id_left, Some(new_id)));
+            }
+        }
+    }
+}
+
+/// Converts a binary tree into a forest with a single tree.
+/// The left child, if any, becomes the first child. Node ids are reassigned in pre-order.
+impl From<&Tree> for Forest {
+    fn from(tree: &Tree) -> Self {
+        let mut forest = Forest::new();
+        forest.append_binary(tree, tree.root, None);
+
+        forest
+    }
+}
+
+impl From<NaryTree> for Forest {
+    fn from(tree: NaryTree) -> Self {
+        tree.forest
+    }
+}
+
+/// A rooted n-ary tree. It's a `Forest` with exactly one root, the node 0.
+pub struct NaryTree {
+    forest: Forest,
+}
+
+impl NaryTree {
+    pub fn with_root(key: i32) -> Self {
+        let mut forest = Forest::new();
+        forest.add_root(key);
+
+        Self { forest }
+    }
+
+    /// Adds a child with the specified `key` after the other children of the node
+    /// with `parent_id` and returns the id of the new node.
+    ///
+    /// # Panics
+    /// Panics if the `parent_id` does not exist.
+    pub fn add_child(&mut self, parent_id: usize, key: i32) -> usize {
+        self.forest.add_child(parent_id, key)
+    }
+
+    /// Returns the number of nodes in the tree.
+    pub fn len(&self) -> usize {
+        self.forest.len()
+    }
+
+    /// Always returns `false`, a tree has at least the root.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns the key of the node `id`.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist.
+    pub fn key(&self, id: usize) -> i32 {
+        self.forest.key(id)
+    }
+
+    /// Returns an iterator over the ids of the children of the node `id`, in insertion order.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist.
+    pub fn children(&self, id: usize) -> Children<'_> {
+        self.forest.children(id)
+    }
+
+    /// Returns the sum of all the keys in the tree
+    pub fn sum(&self) -> i32 {
+        self.forest.sum()
+    }
+
+    /// Returns the number of levels of the tree, 1 for a single node.
+    pub fn height(&self) -> usize {
+        self.forest.height()
+    }
+
+    /// Returns an iterator over the ids of the nodes in pre-order.
+    pub fn preorder(&self) -> Preorder<'_> {
+        self.forest.preorder()
+    }
+
+    /// Returns an iterator over the ids of the nodes level by level.
+    pub fn level_order(&self) -> LevelOrder<'_> {
+        self.forest.level_order()
+    }
+}
+
+/// Converts a binary tree into an n-ary one.
+/// The left child, if any, becomes the first child. Node ids are reassigned in pre-order.
+impl From<&Tree> for NaryTree {
+    fn from(tree: &Tree) -> Self {
+        Self {
+            forest: Forest::from(tree),
+        }
+    }
+}
+
+/// Iterator over the children of a node, see `Forest::children`.
+pub struct Children<'a> {
+    nodes: &'a [NaryNode],
+    next: Option<usize>,
+}
+
+impl Iterator for Children<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let id = self.next?;
+        self.next = self.nodes[id].next_sibling;
+
+        Some(id)
+    }
+}
+
+/// Iterator over the nodes in pre-order, see `Forest::preorder`.
+pub struct Preorder<'a> {
+    forest: &'a Forest,
+    stack: Vec<usize>,
+}
+
+impl Iterator for Preorder<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let id = self.stack.pop()?;
+
+        // Children are pushed in reverse so that the first one is popped first
+        let start = self.stack.len();
+        self.stack.extend(self.forest.children(id));
+        self.stack[start..].reverse();
+
+        Some(id)
+    }
+}
+
+/// Iterator over the nodes level by level, see `Forest::level_order`.
+pub struct LevelOrder<'a> {
+    forest: &'a Forest,
+    queue: VecDeque<usize>,
+}
+
+impl Iterator for LevelOrder<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let id = self.queue.pop_front()?;
+        self.queue.extend(self.forest.children(id));
+
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod forest_tests {
+    use super::*;
+
+    #[test]
+    fn empty_forest() {
+        let forest = Forest::new();
+
+        assert!(forest.is_empty());
+        assert_eq!(forest.sum(), 0);
+        assert_eq!(forest.height(), 0);
+        assert_eq!(forest.preorder().count(), 0);
+        assert_eq!(forest.level_order().count(), 0);
+    }
+
+    #[test]
+    fn several_roots() {
+        let mut forest = Forest::new();
+
+        //    1       5     9
+        //  / | \     |
+        // 2  3  4    6
+        //            |
+        //            7
+        let a = forest.add_root(1); // id 0
+        forest.add_child(a, 2); // id 1
+        forest.add_child(a, 3); // id 2
+        forest.add_child(a, 4); // id 3
+        let b = forest.add_root(5); // id 4
+        let c = forest.add_child(b, 6); // id 5
+        forest.add_child(c, 7); // id 6
+        forest.add_root(9); // id 7
+
+        assert_eq!(forest.roots(), &[0, 4, 7]);
+        assert_eq!(forest.sum(), 37);
+        assert_eq!(forest.height(), 3);
+        assert_eq!(forest.children(a).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(forest.preorder().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(forest.level_order().collect::<Vec<_>>(), vec![0, 4, 7, 1, 2, 3, 5, 6]);
+    }
+
+    #[test]
+    fn nary_tree() {
+        let mut tree = NaryTree::with_root(10);
+
+        //        10
+        //      /  |  \
+        //     1   2   3
+        //    / \      |
+        //   4   5     6
+        tree.add_child(0, 1); // id 1
+        tree.add_child(0, 2); // id 2
+        tree.add_child(0, 3); // id 3
+        tree.add_child(1, 4); // id 4
+        tree.add_child(1, 5); // id 5
+        tree.add_child(3, 6); // id 6
+
+        assert_eq!(tree.len(), 7);
+        assert_eq!(tree.sum(), 31);
+        assert_eq!(tree.height(), 3);
+        assert_eq!(tree.preorder().collect::<Vec<_>>(), vec![0, 1, 4, 5, 2, 3, 6]);
+        assert_eq!(tree.level_order().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5, 6]);
+
+        let forest = Forest::from(tree);
+        assert_eq!(forest.roots(), &[0]);
+        assert_eq!(forest.sum(), 31);
+    }
+
+    #[test]
+    fn from_binary_tree() {
+        let mut tree = Tree::with_root(20);
+
+        //       20
+        //     /    \
+        //   10      9
+        //     \    /  \
+        //      8  10   5
+        tree.add_node(0, 10, true); // id 1
+        tree.add_node(0, 9, false); // id 2
+        tree.add_node(2, 10, true); // id 3
+        tree.add_node(2, 5, false); // id 4
+        tree.add_node(1, 8, false); // id 5
+
+        let nary = NaryTree::from(&tree);
+
+        assert_eq!(nary.len(), 6);
+        assert_eq!(nary.sum(), tree.sum());
+        assert_eq!(nary.height(), 3);
+
+        let keys: Vec<i32> = nary.preorder().map(|id| nary.key(id)).collect();
+        assert_eq!(keys, vec![20, 10, 8, 9, 10, 5]);
+
+        let keys: Vec<i32> = nary.level_order().map(|id| nary.key(id)).collect();
+        assert_eq!(keys, vec![20, 10, 9, 8, 10, 5]);
+    }
+
+    #[test]
+    fn from_random_binary_trees() {
+        use crate::random::{random_tree, Rng};
+
+        let mut rng = Rng::new(28);
+
+        for _ in 0..200 {
+            let n = 1 + rng.below(60);
+            let tree = random_tree(&mut rng, n, -50..=50);
+            let forest = Forest::from(&tree);
+
+            assert_eq!(forest.len(), n);
+            assert_eq!(forest.sum(), tree.sum());
+            assert_eq!(forest.preorder().count(), n);
+            assert_eq!(forest.level_order().count(), n);
+        }
+    }
+}
@@ -1,3 +1,21 @@
+mod aggregates;
+pub mod compact;
+pub mod cursor;
+mod dp;
+pub mod forest;
+pub mod morris;
+mod mutate;
+#[cfg(test)]
+mod oracle;
+pub mod parallel;
+mod paths;
+pub mod persistent;
+pub mod random;
+mod serialize;
+mod views;
+
+use std::sync::OnceLock;
+
 pub struct Node {
     key: i32,
     id_left: Option<usize>,
@@ -16,15 +34,50 @@ impl Node {
 
 pub struct Tree {
     nodes: Vec<Node>,
+    root: usize,
+    cache: Option<aggregates::Aggregates>,
+    /// Parent of every node, `None` for the root, see `parents`.
+    parents: OnceLock<Vec<Option<usize>>>,
 }
 
 impl Tree {
     pub fn with_root(key: i32) -> Self {
         Self {
             nodes: vec![Node::new(key)],
+            root: 0,
+            cache: None,
+            parents: OnceLock::new(),
         }
     }
 
+    /// Returns the id of the root. It's 0 unless a rotation moved another node to the top.
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// Returns the parent of every node, `None` for the root.
+    ///
+    /// The links are kept out of `Node`, which would grow from 40 to 56 bytes: they
+    /// are built in O(n) the first time something walks up the tree, then
+    /// `add_node` and the rotations keep them up to date.
+    pub(crate) fn parents(&self) -> &[Option<usize>] {
+        self.parents.get_or_init(|| {
+            let mut parents = vec![None; self.nodes.len()];
+            for (id, node) in self.nodes.iter().enumerate() {
+                for child in [node.id_left, node.id_right].into_iter().flatten() {
+                    parents[child] = Some(id);
+                }
+            }
+            parents
+        })
+    }
+
+    /// Returns the parent links to update them, building them first if needed.
+    pub(crate) fn parents_mut(&mut self) -> &mut [Option<usize>] {
+        self.parents();
+        self.parents.get_mut().expect("The parent links have just been built")
+    }
+
     /// Adds a child to the node with `parent_id` and returns the id of the new node.
     /// The new node has the specified `key`. The new node is the left child of the
     /// node `parent_id` iff `is_left` is `true`, the right child otherwise.
@@ -39,18 +92,21 @@ impl Tree {
         );
         if is_left {
             assert!(
-                self.nodes[parent_id].id_left == None,
+                self.nodes[parent_id].id_left.is_none(),
                 "Parent node has the left child already set"
             );
         } else {
             assert!(
-                self.nodes[parent_id].id_right == None,
+                self.nodes[parent_id].id_right.is_none(),
                 "Parent node has the right child already set"
             );
         }
 
         let child_id = self.nodes.len();
         self.nodes.push(Node::new(key));
+        if let Some(parents) = self.parents.get_mut() {
+            parents.push(Some(parent_id));
+        }
 
         let child = if is_left {
             &mut self.nodes[parent_id].id_left
@@ -60,12 +116,20 @@ impl Tree {
 
         *child = Some(child_id);
 
+        if self.cache.is_some() {
+            self.cache_push_leaf(parent_id, key);
+        }
+
         child_id
     }
 
     /// Returns the sum of all the keys in the tree
     pub fn sum(&self) -> i32 {
-        self.rec_sum(Some(0))
+        if let Some(cache) = &self.cache {
+            return cache.sum(self.root);
+        }
+
+        self.rec_sum(Some(self.root))
     }
 
     /// A private recursive function that computes the sum of
@@ -86,7 +150,7 @@ impl Tree {
 
     /// Checks if the tree is a Binary Search Tree
     pub fn is_bst(&self) -> bool {
-        self.rec_is_bst(0).0
+        self.rec_is_bst(self.root).0
     }
 
     /// Checks if the given subtree is a Binary Search Tree and returns the maximum or minimum value in the subtree.
@@ -127,14 +191,18 @@ impl Tree {
                 max_key = right.2;
             }
 
-            return (true, min_key, max_key);
+            (true, min_key, max_key)
     }
 
 
     /// Returns the maximum path sum between two leaves in the tree.
     /// If no path is found then it returns None.
     pub fn max_path_sum(&self) -> Option<i32> {
-        self.rec_max_path_sum(Some(0)).1
+        if let Some(cache) = &self.cache {
+            return cache.path(self.root).1;
+        }
+
+        self.rec_max_path_sum(Some(self.root)).1
     }
 
     /// Returns a couple containing the max path sum to a leaf 
@@ -149,68 +217,69 @@ impl Tree {
             let left = self.rec_max_path_sum(node.id_left);
             let right = self.rec_max_path_sum(node.id_right);
 
-            let mut max_sum_child: Option<i32> = None; // Stores the max path sum retrieved form the child nodes
-            let mut result: (i32, Option<i32>) = (node.key, None); // Stores the values that will be returned
+            return max_path_step(node.key, left, right);
+        }
 
-            // This checks whether or not the node is a leaf.
-            // If it is then returns only the node key value as the max path sum to a leaf.
-            if left.0.is_none() && right.0.is_none() {
-                return (Some(node.key), None);
-            }
+        (None, None)
+    }
 
-            // This compares the max path sum retreived from the left and right nodes.
-            if left.1.is_some() && right.1.is_some() {
-                if left.1.unwrap() > right.1.unwrap() {
-                    max_sum_child = left.1;
-                } else {
-                    max_sum_child = right.1;
-                }
-            } else if left.1.is_some() {
-                max_sum_child = left.1;
-            } else if right.1.is_some() {
-                max_sum_child = right.1;
-            }
+    /// Returns the ids of the nodes of the tree in post-order, so that every
+    /// node comes after its children. It uses an explicit stack instead of recursion.
+    fn postorder(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<usize> = vec![self.root];
 
-            // If there is a path to a leaf from both the left and right child nodes
-            // then it adds the greater one to the result
-            if left.0.is_some() && right.0.is_some() {
-                if left.0.unwrap() > right.0.unwrap() {
-                    result.0 += left.0.unwrap();
-                } else {
-                    result.0 += right.0.unwrap();
-                }
+        // Visits node, right, left and then reverses the result
+        while let Some(id) = stack.pop() {
+            order.push(id);
 
-                // this checks if the max sum path evaluated before is greater
-                // than the path that it can be created combining this node and its children
-                if max_sum_child.is_some() {
-                    if left.0.unwrap() + node.key + right.0.unwrap() > max_sum_child.unwrap() {
-                        result.1 = Some(left.0.unwrap() + node.key + right.0.unwrap())
-                    } else {
-                        result.1 = max_sum_child;
-                    }
-                } else {
-                    result.1 = Some(left.0.unwrap() + node.key + right.0.unwrap())
-                }
-            } else if left.0.is_some() {
-                // this runs if the right child doesn't exist
-                result.0 += left.0.unwrap();
-                result.1 = max_sum_child;
-            } else if right.0.is_some() {
-                // this runs if the left child doesn't exist
-                result.0 += right.0.unwrap();
-                result.1 = max_sum_child;
+            let node = &self.nodes[id];
+            if let Some(id_left) = node.id_left {
+                stack.push(id_left);
+            }
+            if let Some(id_right) = node.id_right {
+                stack.push(id_right);
             }
-
-            // returns the evaluated results
-            return (Some(result.0), result.1);
         }
 
-        (None, None)
+        order.reverse();
+        order
     }
 
     //if (left.0.is_none() && left.1.is_none()) && (right.0.is_none() && right.1.is_none()) { return (Some(node.key), None);}
 }
 
+/// Combines the results of `rec_max_path_sum` for the two children of a node
+/// with key `key` into the result for the node itself.
+fn max_path_step(
+    key: i32,
+    left: (Option<i32>, Option<i32>),
+    right: (Option<i32>, Option<i32>),
+) -> (Option<i32>, Option<i32>) {
+    // Stores the max path sum retrieved from the child nodes
+    let max_sum_child = match (left.1, right.1) {
+        (Some(l), Some(r)) => Some(i32::max(l, r)),
+        (l, r) => l.or(r),
+    };
+
+    match (left.0, right.0) {
+        // The node is a leaf: the only path to a leaf is the node itself.
+        (None, None) => (Some(key), None),
+        // If there is a path to a leaf from both the children then the node
+        // can join them, otherwise the best path stays the one of the children
+        (Some(l), Some(r)) => {
+            let through = l + key + r;
+            let best = match max_sum_child {
+                Some(child) => i32::max(through, child),
+                None => through,
+            };
+
+            (Some(key + i32::max(l, r)), Some(best))
+        }
+        (Some(down), None) | (None, Some(down)) => (Some(key + down), max_sum_child),
+    }
+}
+
 #[cfg(test)]
 mod is_bst_tests {
     use super::*;
@@ -219,17 +288,17 @@ mod is_bst_tests {
     fn valid_bts() {
         let mut tree = Tree::with_root(10);
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
 
         tree.add_node(0, 5, true); // id 1
         tree.add_node(0, 22, false); // id 2
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
 
         tree.add_node(1, 7, false); // id 3
         tree.add_node(2, 20, true); // id 4
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
     }
 
     #[test]
@@ -239,21 +308,21 @@ mod is_bst_tests {
         tree.add_node(0, 11, true); // id 1
         tree.add_node(0, 22, false); // id 2
 
-        assert_eq!(tree.is_bst(), false);
+        assert!(!tree.is_bst());
 
         let mut tree1 = Tree::with_root(10);
 
         tree1.add_node(0, 9, true); // id 1
         tree1.add_node(0, 9, false); // id 2
 
-        assert_eq!(tree1.is_bst(), false);
+        assert!(!tree1.is_bst());
     }
 
     #[test]
     fn single_node_bts() {
         let tree = Tree::with_root(10);
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
     }
 
     #[test]
@@ -263,7 +332,7 @@ mod is_bst_tests {
         tree.add_node(0, 9, true); // id 1
         tree.add_node(1, 7, true); // id 3
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
     }
 
     #[test]
@@ -273,7 +342,7 @@ mod is_bst_tests {
         tree.add_node(0, 11, false); // id 1
         tree.add_node(1, 12, false); // id 3
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
     }
 
     #[test]
@@ -290,7 +359,7 @@ mod is_bst_tests {
         tree.add_node(1, 8, true); // id 3
         tree.add_node(1, 15, false); // id 4
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
 
         let mut tree1 = Tree::with_root(20);
 
@@ -304,7 +373,7 @@ mod is_bst_tests {
         tree1.add_node(1, 8, true); // id 3
         tree1.add_node(1, 22, false); // id 4
 
-        assert_eq!(tree1.is_bst(), false);
+        assert!(!tree1.is_bst());
     }
 
     #[test]
@@ -321,7 +390,7 @@ mod is_bst_tests {
         tree.add_node(2, 28, true); // id 3
         tree.add_node(2, 31, false); // id 4
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
 
         let mut tree1 = Tree::with_root(20);
 
@@ -335,7 +404,7 @@ mod is_bst_tests {
         tree1.add_node(2, 19, true); // id 3
         tree1.add_node(2, 31, false); // id 4
 
-        assert_eq!(tree1.is_bst(), false);
+        assert!(!tree1.is_bst());
     }
 
     #[test]
@@ -352,7 +421,7 @@ mod is_bst_tests {
         tree.add_node(1, 16, true); // id 3
         tree.add_node(1, 15, false); // id 4
 
-        assert_eq!(tree.is_bst(), false);
+        assert!(!tree.is_bst());
 
         let mut tree1 = Tree::with_root(20);
 
@@ -366,7 +435,7 @@ mod is_bst_tests {
         tree1.add_node(2, 19, true); // id 3
         tree1.add_node(2, 31, false); // id 4
 
-        assert_eq!(tree1.is_bst(), false);
+        assert!(!tree1.is_bst());
     }
 
     #[test]
@@ -387,7 +456,7 @@ mod is_bst_tests {
         tree.add_node(3, 1, true); // id 5
         tree.add_node(4, 13, false); // id 6
 
-        assert_eq!(tree.is_bst(), false);
+        assert!(!tree.is_bst());
 
         let mut tree = Tree::with_root(10);
 
@@ -405,7 +474,7 @@ mod is_bst_tests {
         tree.add_node(3, 1, true); // id 5
         tree.add_node(4, 7, false); // id 6
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
     }
 
     #[test]
@@ -426,7 +495,7 @@ mod is_bst_tests {
         tree.add_node(3, 9, true); // id 5
         tree.add_node(4, 22, false); // id 6
 
-        assert_eq!(tree.is_bst(), false);
+        assert!(!tree.is_bst());
 
         let mut tree = Tree::with_root(10);
 
@@ -444,7 +513,7 @@ mod is_bst_tests {
         tree.add_node(3, 11, true); // id 5
         tree.add_node(4, 22, false); // id 6
 
-        assert_eq!(tree.is_bst(), true);
+        assert!(tree.is_bst());
     }
 }
 
@@ -0,0 +1,195 @@
+use crate::Tree;
+
+/// In-order iterator over the keys of a `Tree` that uses O(1) extra space.
+///
+/// It's a Morris traversal: to come back from a left subtree, it temporarily sets
+/// the right link of the in-order predecessor of a node, which is always missing,
+/// to the node itself, and removes it on the way back. That's why it borrows the
+/// tree mutably. When the iterator is dropped before the end, the traversal is
+/// completed without yielding, so the tree is restored exactly. Leaking the
+/// iterator (e.g. with `std::mem::forget`) leaves the temporary links in place.
+pub struct MorrisInorder<'a> {
+    tree: &'a mut Tree,
+    current: Option<usize>,
+}
+
+impl Iterator for MorrisInorder<'_> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        let nodes = &mut self.tree.nodes;
+
+        while let Some(id) = self.current {
+            let Some(id_left) = nodes[id].id_left else {
+                // No left subtree: the node is next, then its right subtree
+                self.current = nodes[id].id_right;
+                return Some(nodes[id].key);
+            };
+
+            // The predecessor is the rightmost node of the left subtree,
+            // unless the walk finds the temporary link back to `id`
+            let mut predecessor = id_left;
+            while let Some(next) = nodes[predecessor].id_right {
+                if next == id {
+                    break;
+                }
+                predecessor = next;
+            }
+
+            if nodes[predecessor].id_right.is_none() {
+                // First time on `id`: links back to it and visits the left subtree
+                nodes[predecessor].id_right = Some(id);
+                self.current = Some(id_left);
+            } else {
+                // Back from the left subtree: removes the link, the node is next
+                nodes[predecessor].id_right = None;
+                self.current = nodes[id].id_right;
+                return Some(nodes[id].key);
+            }
+        }
+
+        None
+    }
+}
+
+impl Drop for MorrisInorder<'_> {
+    fn drop(&mut self) {
+        // Completes the traversal, which removes every temporary link left
+        for _ in self.by_ref() {}
+    }
+}
+
+impl Tree {
+    /// Returns an iterator over the keys in in-order that uses O(1) extra space,
+    /// see `MorrisInorder`. The tree is left unchanged once the iterator is dropped.
+    pub fn morris_inorder(&mut self) -> MorrisInorder<'_> {
+        let root = self.root;
+
+        MorrisInorder {
+            tree: self,
+            current: Some(root),
+        }
+    }
+
+    /// Checks if the tree is a Binary Search Tree using O(1) extra space: the keys
+    /// must come out of a Morris in-order traversal in non-decreasing order.
+    /// It gives the same result of `is_bst`, and the tree is left unchanged.
+    pub fn is_bst_constant_space(&mut self) -> bool {
+        let mut previous: Option<i32> = None;
+
+        for key in self.morris_inorder() {
+            if previous.is_some_and(|previous| previous > key) {
+                return false;
+            }
+            previous = Some(key);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod morris_tests {
+    use super::*;
+    use crate::random::{random_bst, random_chain, random_tree, Rng};
+
+    /// Key, left and right link of a node
+    type Links = (i32, Option<usize>, Option<usize>);
+
+    /// Returns all the links of the tree, to check that it's restored.
+    fn links(tree: &Tree) -> Vec<Links> {
+        tree.nodes
+            .iter()
+            .map(|node| (node.key, node.id_left, node.id_right))
+            .collect()
+    }
+
+    #[test]
+    fn inorder_keys() {
+        let mut tree = Tree::with_root(10);
+
+        //         10
+        //       /    \
+        //      5     15
+        //    /  \
+        //   2   6
+        //  /     \
+        // 1      7
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, 15, false); // id 2
+        tree.add_node(1, 2, true); // id 3
+        tree.add_node(1, 6, false); // id 4
+        tree.add_node(3, 1, true); // id 5
+        tree.add_node(4, 7, false); // id 6
+
+        let before = links(&tree);
+
+        let keys: Vec<i32> = tree.morris_inorder().collect();
+        assert_eq!(keys, vec![1, 2, 5, 6, 7, 10, 15]);
+        assert_eq!(links(&tree), before);
+
+        assert!(tree.is_bst_constant_space());
+        assert_eq!(links(&tree), before);
+    }
+
+    #[test]
+    fn early_exit_restores_tree() {
+        let mut rng = Rng::new(35);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(50);
+            let mut tree = random_tree(&mut rng, n, -50..=50);
+            let before = links(&tree);
+
+            let stop = rng.below(n + 1);
+            let taken: Vec<i32> = tree.morris_inorder().take(stop).collect();
+
+            assert_eq!(taken.len(), stop);
+            assert_eq!(links(&tree), before);
+        }
+    }
+
+    #[test]
+    fn same_result_as_is_bst() {
+        let mut rng = Rng::new(36);
+
+        for _ in 0..1000 {
+            let n = 1 + rng.below(40);
+            let mut tree = match rng.below(3) {
+                0 => random_tree(&mut rng, n, -5..=5),
+                1 => random_bst(&mut rng, n, -50..=50),
+                _ => random_chain(&mut rng, n, -50..=50),
+            };
+
+            // Breaks the BST property half of the times
+            if rng.coin() {
+                let id = rng.below(n);
+                tree.set_key(id, rng.key(-50..=50));
+            }
+
+            let before = links(&tree);
+            assert_eq!(tree.is_bst_constant_space(), tree.is_bst());
+            assert_eq!(links(&tree), before);
+        }
+    }
+
+    #[test]
+    fn not_bst_stops_early() {
+        let mut tree = Tree::with_root(20);
+
+        //       20
+        //     /    \
+        //   10      21
+        //  /  \
+        // 16   15
+        tree.add_node(0, 10, true); // id 1
+        tree.add_node(0, 21, false); // id 2
+        tree.add_node(1, 16, true); // id 3
+        tree.add_node(1, 15, false); // id 4
+
+        let before = links(&tree);
+
+        assert!(!tree.is_bst_constant_space());
+        assert_eq!(links(&tree), before);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::Tree;
+
+impl Tree {
+    /// Swaps the left and the right child of the node `id`. O(1).
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist.
+    pub fn swap_children(&mut self, id: usize) {
+        assert!(id < self.nodes.len(), "Node id does not exist");
+
+        let node = &mut self.nodes[id];
+        std::mem::swap(&mut node.id_left, &mut node.id_right);
+
+        // Sums and max path sums don't depend on the order of the children,
+        // so the cached values, if any, are still valid.
+    }
+
+    /// Mirrors the tree, swapping the children of every node. O(n).
+    pub fn mirror(&mut self) {
+        for node in self.nodes.iter_mut() {
+            std::mem::swap(&mut node.id_left, &mut node.id_right);
+        }
+    }
+
+    /// Rotates left the subtree rooted at `id`: its right child takes its place
+    /// and `id` becomes the left child of it. The in-order sequence of keys
+    /// doesn't change, so a Binary Search Tree stays valid.
+    ///
+    /// ```text
+    ///     id                 r
+    ///    /  \               / \
+    ///   a    r     =>     id   c
+    ///       / \          /  \
+    ///      b   c        a    b
+    /// ```
+    ///
+    /// Runs in O(1), plus O(n) the first time to build the parent links, see
+    /// `parents`, and O(h) to repair the cached aggregates if enabled.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist or has no right child.
+    pub fn rotate_left(&mut self, id: usize) {
+        assert!(id < self.nodes.len(), "Node id does not exist");
+        let pivot = self.nodes[id]
+            .id_right
+            .expect("Node has no right child to rotate");
+        let parent = self.parents()[id];
+
+        let inner = self.nodes[pivot].id_left;
+        self.nodes[id].id_right = inner;
+        self.nodes[pivot].id_left = Some(id);
+
+        self.replace_in_parent(id, pivot, inner, parent);
+    }
+
+    /// Rotates right the subtree rooted at `id`: its left child takes its place
+    /// and `id` becomes the right child of it. The in-order sequence of keys
+    /// doesn't change, so a Binary Search Tree stays valid.
+    ///
+    /// ```text
+    ///       id             l
+    ///      /  \           / \
+    ///     l    c   =>    a   id
+    ///    / \                /  \
+    ///   a   b              b    c
+    /// ```
+    ///
+    /// Runs in O(1), plus O(n) the first time to build the parent links, see
+    /// `parents`, and O(h) to repair the cached aggregates if enabled.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist or has no left child.
+    pub fn rotate_right(&mut self, id: usize) {
+        assert!(id < self.nodes.len(), "Node id does not exist");
+        let pivot = self.nodes[id]
+            .id_left
+            .expect("Node has no left child to rotate");
+        let parent = self.parents()[id];
+
+        let inner = self.nodes[pivot].id_right;
+        self.nodes[id].id_left = inner;
+        self.nodes[pivot].id_right = Some(id);
+
+        self.replace_in_parent(id, pivot, inner, parent);
+    }
+
+    /// Completes a rotation that moved `pivot` in the place of `id`, with
+    /// `inner` moved from `pivot` to `id`: fixes the parent links and the
+    /// link from `parent`, the old parent of `id`, which may be the root of the tree.
+    /// The parent links must have been built before the rotation changed the child links.
+    fn replace_in_parent(&mut self, id: usize, pivot: usize, inner: Option<usize>, parent: Option<usize>) {
+        let parents = self.parents_mut();
+        if let Some(inner) = inner {
+            parents[inner] = Some(id);
+        }
+
+        parents[pivot] = parent;
+        parents[id] = Some(pivot);
+
+        match parent {
+            Some(parent) => {
+                let node = &mut self.nodes[parent];
+                if node.id_left == Some(id) {
+                    node.id_left = Some(pivot);
+                } else {
+                    node.id_right = Some(pivot);
+                }
+            }
+            None => self.root = pivot,
+        }
+
+        // Only `id` and `pivot` have new children, the ancestors have new values
+        if self.cache.is_some() {
+            self.refresh(id);
+            self.refresh(pivot);
+            if let Some(parent) = parent {
+                self.repair(parent);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod mutate_tests {
+    use super::*;
+    use crate::oracle::parents;
+    use crate::random::{random_bst, random_tree, Rng};
+
+    /// Returns the keys in in-order, with an explicit stack.
+    fn inorder(tree: &Tree) -> Vec<i32> {
+        let mut keys = Vec::new();
+        let mut stack = Vec::new();
+        let mut current = Some(tree.root);
+
+        loop {
+            while let Some(id) = current {
+                stack.push(id);
+                current = tree.nodes[id].id_left;
+            }
+
+            let Some(id) = stack.pop() else {
+                return keys;
+            };
+
+            keys.push(tree.nodes[id].key);
+            current = tree.nodes[id].id_right;
+        }
+    }
+
+    /// Checks that the parent links kept by the tree match the child links.
+    fn assert_parents(tree: &Tree) {
+        let expected = parents(tree);
+        assert_eq!(tree.parents(), expected);
+        assert_eq!(tree.parents()[tree.root], None);
+    }
+
+    #[test]
+    fn rotate_root() {
+        let mut tree = Tree::with_root(10);
+
+        //     10             15
+        //    /  \           /  \
+        //   5    15   =>   10   20
+        //       /  \      /  \
+        //     12    20   5   12
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, 15, false); // id 2
+        tree.add_node(2, 12, true); // id 3
+        tree.add_node(2, 20, false); // id 4
+
+        tree.rotate_left(0);
+
+        assert_eq!(tree.root(), 2);
+        assert_eq!(tree.nodes[2].id_left, Some(0));
+        assert_eq!(tree.nodes[2].id_right, Some(4));
+        assert_eq!(tree.nodes[0].id_right, Some(3));
+        assert_parents(&tree);
+        assert!(tree.is_bst());
+        assert_eq!(tree.sum(), 62);
+
+        tree.rotate_right(2);
+
+        assert_eq!(tree.root(), 0);
+        assert_eq!(inorder(&tree), vec![5, 10, 12, 15, 20]);
+        assert_parents(&tree);
+    }
+
+    #[test]
+    fn rotate_inner_node() {
+        let mut tree = Tree::with_root(20);
+
+        //       20              20
+        //     /    \          /    \
+        //   10      21  =>   8      21
+        //  /  \               \
+        // 8   15              10
+        //                       \
+        //                       15
+        tree.add_node(0, 10, true); // id 1
+        tree.add_node(0, 21, false); // id 2
+        tree.add_node(1, 8, true); // id 3
+        tree.add_node(1, 15, false); // id 4
+
+        tree.rotate_right(1);
+
+        assert_eq!(tree.root(), 0);
+        assert_eq!(tree.nodes[0].id_left, Some(3));
+        assert_eq!(tree.nodes[3].id_right, Some(1));
+        assert_eq!(tree.nodes[1].id_left, None);
+        assert_parents(&tree);
+        assert!(tree.is_bst());
+    }
+
+    #[test]
+    #[should_panic(expected = "Node has no right child to rotate")]
+    fn rotate_without_child() {
+        let mut tree = Tree::with_root(10);
+        tree.add_node(0, 5, true);
+
+        tree.rotate_left(0);
+    }
+
+    #[test]
+    fn swap_and_mirror() {
+        let mut tree = Tree::with_root(3);
+
+        //            3               3
+        //         /    \           /   \
+        //       4       5   =>    5     4
+        //      /  \                    /  \
+        //    -10   4                  4   -10
+        tree.add_node(0, 4, true); // id 1
+        tree.add_node(0, 5, false); // id 2
+        tree.add_node(1, -10, true); // id 3
+        tree.add_node(1, 4, false); // id 4
+
+        tree.mirror();
+        assert_eq!(inorder(&tree), vec![5, 3, 4, 4, -10]);
+        assert_eq!(tree.max_path_sum(), Some(16));
+
+        tree.swap_children(1);
+        assert_eq!(inorder(&tree), vec![5, 3, -10, 4, 4]);
+
+        tree.mirror();
+        tree.swap_children(1);
+        assert_eq!(inorder(&tree), vec![-10, 4, 4, 3, 5]);
+        assert_parents(&tree);
+    }
+
+    #[test]
+    fn rotations_keep_bst_and_sum() {
+        let mut rng = Rng::new(32);
+
+        for _ in 0..500 {
+            let n = 1 + rng.below(40);
+            let mut tree = random_bst(&mut rng, n, -50..=50);
+            let sum = tree.sum();
+            let keys = inorder(&tree);
+
+            for _ in 0..20 {
+                let id = rng.below(n);
+                if rng.coin() {
+                    if tree.nodes[id].id_right.is_some() {
+                        tree.rotate_left(id);
+                    }
+                } else if tree.nodes[id].id_left.is_some() {
+                    tree.rotate_right(id);
+                }
+
+                assert!(tree.is_bst());
+                assert_eq!(tree.sum(), sum);
+                assert_eq!(inorder(&tree), keys);
+            }
+
+            assert_parents(&tree);
+        }
+    }
+
+    #[test]
+    fn mutations_with_cache() {
+        let mut rng = Rng::new(33);
+
+        for _ in 0..500 {
+            let n = 1 + rng.below(40);
+            let mut tree = random_tree(&mut rng, n, -50..=50);
+            tree.enable_cached_aggregates();
+
+            for _ in 0..20 {
+                let id = rng.below(n);
+                match rng.below(5) {
+                    0 if tree.nodes[id].id_right.is_some() => tree.rotate_left(id),
+                    1 if tree.nodes[id].id_left.is_some() => tree.rotate_right(id),
+                    2 => tree.swap_children(id),
+                    3 => tree.mirror(),
+                    // Once built by a rotation, the parent links must follow the new leaves
+                    4 if tree.nodes[id].id_left.is_none() => {
+                        tree.add_node(id, rng.key(-50..=50), true);
+                    }
+                    _ => {}
+                }
+
+                assert_eq!(tree.sum(), tree.rec_sum(Some(tree.root)));
+                assert_eq!(tree.max_path_sum(), tree.rec_max_path_sum(Some(tree.root)).1);
+            }
+
+            assert_parents(&tree);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Brute-force reference implementations used to cross-check the methods of `Tree`.
+//! They favour obviousness over speed and are only compiled for tests.
+
+use crate::Tree;
+
+/// Returns the parent of every node, `None` for the root.
+pub(crate) fn parents(tree: &Tree) -> Vec<Option<usize>> {
+    let mut parents = vec![None; tree.nodes.len()];
+
+    for (id, node) in tree.nodes.iter().enumerate() {
+        for child in [node.id_left, node.id_right].into_iter().flatten() {
+            parents[child] = Some(id);
+        }
+    }
+
+    parents
+}
+
+/// Returns the ids of all the nodes in the subtree rooted at `id`.
+pub(crate) fn subtree(tree: &Tree, id: Option<usize>) -> Vec<usize> {
+    let mut ids = Vec::new();
+    let mut stack: Vec<usize> = id.into_iter().collect();
+
+    while let Some(id) = stack.pop() {
+        ids.push(id);
+
+        let node = &tree.nodes[id];
+        stack.extend(node.id_left);
+        stack.extend(node.id_right);
+    }
+
+    ids
+}
+
+/// Checks the definition directly: every key in the left subtree of a node is not
+/// greater than its key, and every key in the right subtree is not smaller.
+pub(crate) fn is_bst(tree: &Tree) -> bool {
+    tree.nodes.iter().all(|node| {
+        subtree(tree, node.id_left)
+            .iter()
+            .all(|&id| tree.nodes[id].key <= node.key)
+            && subtree(tree, node.id_right)
+                .iter()
+                .all(|&id| tree.nodes[id].key >= node.key)
+    })
+}
+
+/// Returns the sum of the keys on the path between the nodes `a` and `b`.
+pub(crate) fn path_sum(tree: &Tree, parents: &[Option<usize>], a: usize, b: usize) -> i32 {
+    let ancestors = |mut id: usize| {
+        let mut path = vec![id];
+        while let Some(parent) = parents[id] {
+            path.push(parent);
+            id = parent;
+        }
+        path
+    };
+
+    let from_a = ancestors(a);
+    let from_b = ancestors(b);
+
+    // The lowest common ancestor is the first ancestor of `a` that is also an ancestor of `b`
+    let lca_index = from_a.iter().position(|id| from_b.contains(id)).unwrap();
+    let lca = from_a[lca_index];
+
+    let mut sum: i32 = from_a[..=lca_index].iter().map(|&id| tree.nodes[id].key).sum();
+    for &id in from_b.iter().take_while(|&&id| id != lca) {
+        sum += tree.nodes[id].key;
+    }
+
+    sum
+}
+
+/// Enumerates all the pairs of distinct leaves and returns the maximum path sum between them.
+pub(crate) fn max_path_sum(tree: &Tree) -> Option<i32> {
+    let parents = parents(tree);
+    let leaves: Vec<usize> = (0..tree.nodes.len())
+        .filter(|&id| tree.nodes[id].id_left.is_none() && tree.nodes[id].id_right.is_none())
+        .collect();
+
+    let mut best: Option<i32> = None;
+    for (i, &a) in leaves.iter().enumerate() {
+        for &b in &leaves[i + 1..] {
+            let sum = path_sum(tree, &parents, a, b);
+            best = Some(best.map_or(sum, |best| best.max(sum)));
+        }
+    }
+
+    best
+}
+
+/// Enumerates all the subsets of nodes with no two adjacent nodes and returns
+/// the maximum sum of their keys.
+pub(crate) fn max_independent_set(tree: &Tree) -> i64 {
+    let parents = parents(tree);
+
+    subsets(tree.nodes.len())
+        .filter(|chosen| {
+            (0..chosen.len())
+                .all(|id| !chosen[id] || parents[id].is_none_or(|parent| !chosen[parent]))
+        })
+        .map(|chosen| subset_sum(tree, &chosen))
+        .max()
+        .unwrap()
+}
+
+/// Enumerates all the subsets of nodes that touch every edge and returns
+/// the minimum sum of their keys.
+pub(crate) fn min_vertex_cover(tree: &Tree) -> i64 {
+    let parents = parents(tree);
+
+    subsets(tree.nodes.len())
+        .filter(|chosen| {
+            (0..chosen.len())
+                .all(|id| parents[id].is_none_or(|parent| chosen[id] || chosen[parent]))
+        })
+        .map(|chosen| subset_sum(tree, &chosen))
+        .min()
+        .unwrap()
+}
+
+/// Returns all the 2^n subsets of n nodes, as flags of the chosen ids.
+fn subsets(n: usize) -> impl Iterator<Item = Vec<bool>> {
+    (0..1u32 << n).map(move |mask| (0..n).map(|id| mask >> id & 1 == 1).collect())
+}
+
+fn subset_sum(tree: &Tree, chosen: &[bool]) -> i64 {
+    (0..chosen.len())
+        .filter(|&id| chosen[id])
+        .map(|id| tree.nodes[id].key as i64)
+        .sum()
+}
+
+#[cfg(test)]
+mod differential_tests {
+    use super::*;
+    use crate::random::{random_bst, random_chain, random_tree, Rng};
+
+    #[test]
+    fn random_trees() {
+        let mut rng = Rng::new(1);
+
+        for _ in 0..3000 {
+            let n = 1 + rng.below(40);
+            let tree = random_tree(&mut rng, n, -50..=50);
+
+            assert_eq!(tree.is_bst(), is_bst(&tree));
+            assert_eq!(tree.max_path_sum(), max_path_sum(&tree));
+        }
+    }
+
+    #[test]
+    fn random_bsts() {
+        let mut rng = Rng::new(2);
+
+        for _ in 0..3000 {
+            let n = 1 + rng.below(40);
+            let mut tree = random_bst(&mut rng, n, -50..=50);
+
+            assert!(tree.is_bst());
+            assert!(is_bst(&tree));
+            assert_eq!(tree.max_path_sum(), max_path_sum(&tree));
+
+            // A single changed key may or may not break the BST property
+            let id = rng.below(n);
+            tree.set_key(id, rng.key(-50..=50));
+            assert_eq!(tree.is_bst(), is_bst(&tree));
+        }
+    }
+
+    #[test]
+    fn random_chains() {
+        let mut rng = Rng::new(3);
+
+        for _ in 0..3000 {
+            let n = 1 + rng.below(40);
+            let tree = random_chain(&mut rng, n, -50..=50);
+
+            assert_eq!(tree.is_bst(), is_bst(&tree));
+            assert_eq!(tree.max_path_sum(), max_path_sum(&tree));
+            assert_eq!(tree.max_path_sum(), None);
+        }
+    }
+
+    #[test]
+    fn small_key_ranges() {
+        // Few distinct keys make equal keys frequent, which is the tricky case for is_bst
+        let mut rng = Rng::new(4);
+
+        for _ in 0..3000 {
+            let n = 1 + rng.below(12);
+            let tree = random_tree(&mut rng, n, 0..=2);
+
+            assert_eq!(tree.is_bst(), is_bst(&tree));
+            assert_eq!(tree.max_path_sum(), max_path_sum(&tree));
+        }
+    }
+}
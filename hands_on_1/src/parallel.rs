@@ -0,0 +1,252 @@
+use crate::{max_path_step, Node, Tree};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Below this number of nodes the parallel methods run the sequential ones,
+/// since spawning threads would cost more than the visit.
+pub const PARALLEL_THRESHOLD: usize = 1 << 16;
+
+/// Number of subtrees to hand out to every thread, so that a thread that gets
+/// small subtrees can pick more work from the others.
+const TASKS_PER_THREAD: usize = 8;
+
+/// Maximum number of nodes visited sequentially while looking for subtrees
+/// to split, which bounds the work wasted on degenerate trees.
+const MAX_SPLIT_NODES: usize = 1 << 12;
+
+/// Result of `rec_is_bst` for a subtree: (is_bst, min_key, max_key)
+type BstResult = (bool, i32, i32);
+
+/// Result of `rec_max_path_sum` for a subtree
+type PathResult = (Option<i32>, Option<i32>);
+
+impl Tree {
+    /// Returns the sum of all the keys in the tree, like `sum`, splitting the work
+    /// among the available cores.
+    pub fn par_sum(&self) -> i32 {
+        if self.nodes.len() < PARALLEL_THRESHOLD {
+            return self.sum();
+        }
+
+        self.par_fold(available_threads(), sum_step)
+    }
+
+    /// Checks if the tree is a Binary Search Tree, like `is_bst`, splitting the work
+    /// among the available cores.
+    pub fn par_is_bst(&self) -> bool {
+        if self.nodes.len() < PARALLEL_THRESHOLD {
+            return self.is_bst();
+        }
+
+        self.par_fold(available_threads(), bst_step).0
+    }
+
+    /// Returns the maximum path sum between two leaves in the tree, like
+    /// `max_path_sum`, splitting the work among the available cores.
+    pub fn par_max_path_sum(&self) -> Option<i32> {
+        if self.nodes.len() < PARALLEL_THRESHOLD {
+            return self.max_path_sum();
+        }
+
+        self.par_fold(available_threads(), path_step).1
+    }
+
+    /// Computes `step` bottom-up over the whole tree with `threads` threads.
+    ///
+    /// The top of the tree is expanded level by level until there are enough
+    /// subtrees for every thread. The subtrees are folded in parallel, then the
+    /// results are combined sequentially over the expanded nodes.
+    fn par_fold<T, F>(&self, threads: usize, step: F) -> T
+    where
+        T: Copy + Send,
+        F: Fn(&Tree, &Node, Option<T>, Option<T>) -> T + Sync,
+    {
+        let target = threads * TASKS_PER_THREAD;
+
+        // Nodes above the split, in level order, and roots of the subtrees to fold
+        let mut top: Vec<usize> = Vec::new();
+        let mut frontier: Vec<usize> = vec![self.root];
+
+        while frontier.len() < target && top.len() < MAX_SPLIT_NODES {
+            let mut next: Vec<usize> = Vec::with_capacity(2 * frontier.len());
+            let mut split = false;
+
+            for id in frontier {
+                let node = &self.nodes[id];
+                if node.id_left.is_none() && node.id_right.is_none() {
+                    next.push(id);
+                    continue;
+                }
+
+                split = true;
+                top.push(id);
+                next.extend(node.id_left);
+                next.extend(node.id_right);
+            }
+
+            frontier = next;
+            if !split {
+                break;
+            }
+        }
+
+        // Every thread picks the next subtree to fold until none is left
+        let next_task = AtomicUsize::new(0);
+        let folded: Mutex<HashMap<usize, T>> = Mutex::new(HashMap::with_capacity(frontier.len()));
+
+        std::thread::scope(|scope| {
+            for _ in 0..threads.min(frontier.len()) {
+                scope.spawn(|| loop {
+                    let task = next_task.fetch_add(1, Ordering::Relaxed);
+                    let Some(&id) = frontier.get(task) else {
+                        break;
+                    };
+
+                    let result = self.fold_subtree(id, &step);
+                    folded.lock().unwrap().insert(id, result);
+                });
+            }
+        });
+
+        // Children come after their parent in level order, so the top is combined backwards
+        let mut results = folded.into_inner().unwrap();
+        for &id in top.iter().rev() {
+            let node = &self.nodes[id];
+            let left = node.id_left.map(|id| results[&id]);
+            let right = node.id_right.map(|id| results[&id]);
+
+            results.insert(id, step(self, node, left, right));
+        }
+
+        results[&self.root]
+    }
+
+    /// Computes `step` bottom-up over the subtree rooted at `id`, with an explicit stack.
+    fn fold_subtree<T, F>(&self, id: usize, step: &F) -> T
+    where
+        T: Copy,
+        F: Fn(&Tree, &Node, Option<T>, Option<T>) -> T,
+    {
+        // Nodes to visit, with a flag set once their children have been visited
+        let mut stack: Vec<(usize, bool)> = vec![(id, false)];
+        // Results of the visited subtrees whose parent is still to be combined
+        let mut results: Vec<T> = Vec::new();
+
+        while let Some((id, children_done)) = stack.pop() {
+            let node = &self.nodes[id];
+
+            if !children_done {
+                stack.push((id, true));
+                stack.extend(node.id_right.map(|id| (id, false)));
+                stack.extend(node.id_left.map(|id| (id, false)));
+                continue;
+            }
+
+            // The right subtree has been visited last, so its result is on top
+            let right = node.id_right.map(|_| results.pop().unwrap());
+            let left = node.id_left.map(|_| results.pop().unwrap());
+
+            results.push(step(self, node, left, right));
+        }
+
+        results.pop().unwrap()
+    }
+}
+
+fn available_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+/// Same computation of `rec_sum`
+fn sum_step(_tree: &Tree, node: &Node, left: Option<i32>, right: Option<i32>) -> i32 {
+    left.unwrap_or(0) + right.unwrap_or(0) + node.key
+}
+
+/// Same computation of `rec_is_bst`
+fn bst_step(tree: &Tree, node: &Node, left: Option<BstResult>, right: Option<BstResult>) -> BstResult {
+    let mut min_key: i32 = node.key;
+    let mut max_key: i32 = node.key;
+
+    if let Some(id_left) = node.id_left {
+        let left = left.unwrap();
+        if node.key < tree.nodes[id_left].key || !left.0 || left.2 > node.key {
+            return (false, -1, -1);
+        }
+        min_key = left.1;
+    }
+
+    if let Some(id_right) = node.id_right {
+        let right = right.unwrap();
+        if node.key > tree.nodes[id_right].key || !right.0 || right.1 < node.key {
+            return (false, -1, -1);
+        }
+        max_key = right.2;
+    }
+
+    (true, min_key, max_key)
+}
+
+/// Same computation of `rec_max_path_sum`
+fn path_step(_tree: &Tree, node: &Node, left: Option<PathResult>, right: Option<PathResult>) -> PathResult {
+    max_path_step(
+        node.key,
+        left.unwrap_or((None, None)),
+        right.unwrap_or((None, None)),
+    )
+}
+
+#[cfg(test)]
+mod parallel_tests {
+    use super::*;
+    use crate::random::{random_bst, random_chain, random_tree, Rng};
+
+    /// Checks the parallel fold against the sequential methods, forcing the split
+    /// even on small trees.
+    fn assert_same_results(tree: &Tree, threads: usize) {
+        assert_eq!(tree.par_fold(threads, sum_step), tree.sum());
+        assert_eq!(tree.par_fold(threads, bst_step).0, tree.is_bst());
+        assert_eq!(tree.par_fold(threads, path_step).1, tree.max_path_sum());
+    }
+
+    #[test]
+    fn small_random_trees() {
+        let mut rng = Rng::new(36);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(200);
+            let threads = 1 + rng.below(4);
+            let tree = match rng.below(3) {
+                0 => random_tree(&mut rng, n, -50..=50),
+                1 => random_bst(&mut rng, n, -50..=50),
+                _ => random_chain(&mut rng, n, -50..=50),
+            };
+
+            assert_same_results(&tree, threads);
+        }
+    }
+
+    #[test]
+    fn above_threshold() {
+        let mut rng = Rng::new(37);
+
+        let tree = random_tree(&mut rng, 2 * PARALLEL_THRESHOLD, -1000..=1000);
+        assert_eq!(tree.par_sum(), tree.sum());
+        assert_eq!(tree.par_is_bst(), tree.is_bst());
+        assert_eq!(tree.par_max_path_sum(), tree.max_path_sum());
+
+        let tree = random_bst(&mut rng, 2 * PARALLEL_THRESHOLD, -1000..=1000);
+        assert!(tree.par_is_bst());
+        assert_eq!(tree.par_sum(), tree.sum());
+        assert_eq!(tree.par_max_path_sum(), tree.max_path_sum());
+    }
+
+    #[test]
+    fn degenerate_tree() {
+        let mut rng = Rng::new(38);
+
+        // Deeper than the nodes visited while splitting
+        let tree = random_chain(&mut rng, 3 * MAX_SPLIT_NODES, -1000..=1000);
+        assert_same_results(&tree, 4);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::Tree;
+use std::collections::HashMap;
+
+impl Tree {
+    /// Returns the number of downward paths whose keys sum to `target`. A downward
+    /// path goes from a node to one of its descendants, or is a single node.
+    ///
+    /// The sums are computed as `i64`, so they never overflow.
+    ///
+    /// # Complexity
+    /// O(n) expected time: a single depth-first visit keeps, in a hash map, how many
+    /// ancestors have every prefix sum from the root.
+    pub fn count_downward_paths_with_sum(&self, target: i64) -> u64 {
+        // Number of nodes on the current root path (plus the empty prefix) by prefix sum
+        let mut prefixes: HashMap<i64, u64> = HashMap::new();
+        prefixes.insert(0, 1);
+
+        let mut count: u64 = 0;
+
+        // (node id, prefix sum up to its parent, true when the node is being left)
+        let mut stack: Vec<(usize, i64, bool)> = vec![(self.root, 0, false)];
+
+        while let Some((id, before, leaving)) = stack.pop() {
+            let node = &self.nodes[id];
+            let prefix = before + node.key as i64;
+
+            if leaving {
+                *prefixes.get_mut(&prefix).unwrap() -= 1;
+                continue;
+            }
+
+            // Every ancestor prefix equal to `prefix - target` starts a path ending here
+            count += prefixes.get(&(prefix - target)).copied().unwrap_or(0);
+            *prefixes.entry(prefix).or_insert(0) += 1;
+
+            stack.push((id, before, true));
+            for child in [node.id_left, node.id_right].into_iter().flatten() {
+                stack.push((child, prefix, false));
+            }
+        }
+
+        count
+    }
+
+    /// Returns the number of paths between any two nodes, including single nodes,
+    /// whose keys sum to `target`. A path and its reverse are counted once.
+    ///
+    /// The sums are computed as `i64`, so they never overflow.
+    ///
+    /// # Complexity
+    /// O(n log n) expected time and O(n) space, using centroid decomposition: every
+    /// path is counted at the first centroid it passes through, and every node belongs
+    /// to the components of O(log n) centroids.
+    pub fn count_all_paths_with_sum(&self, target: i64) -> u64 {
+        let n = self.nodes.len();
+        let neighbours = self.neighbours();
+
+        let mut removed = vec![false; n];
+        let mut size = vec![0usize; n];
+        let mut count: u64 = 0;
+
+        let mut components: Vec<usize> = vec![self.root];
+        while let Some(start) = components.pop() {
+            let centroid = find_centroid(&neighbours, &removed, &mut size, start);
+            let key = self.nodes[centroid].key as i64;
+
+            // The path made of the centroid alone
+            if key == target {
+                count += 1;
+            }
+
+            // Sums of the paths from the centroid (excluded) into the components
+            // already visited. The empty path stands for paths ending at the centroid.
+            let mut seen: HashMap<i64, u64> = HashMap::new();
+            seen.insert(0, 1);
+
+            for &child in neighbours[centroid].iter().flatten() {
+                if removed[child] {
+                    continue;
+                }
+
+                let sums = path_sums(self, &neighbours, &removed, child, centroid);
+
+                for &sum in &sums {
+                    count += seen.get(&(target - key - sum)).copied().unwrap_or(0);
+                }
+                for sum in sums {
+                    *seen.entry(sum).or_insert(0) += 1;
+                }
+            }
+
+            removed[centroid] = true;
+            for &child in neighbours[centroid].iter().flatten() {
+                if !removed[child] {
+                    components.push(child);
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Returns, for every node, its left child, its right child and its parent.
+    fn neighbours(&self) -> Vec<[Option<usize>; 3]> {
+        self.nodes
+            .iter()
+            .zip(self.parents())
+            .map(|(node, &parent)| [node.id_left, node.id_right, parent])
+            .collect()
+    }
+}
+
+/// Returns the nodes of the component of `start` that aren't removed, in
+/// visit order, with the node they have been reached from.
+fn component(
+    neighbours: &[[Option<usize>; 3]],
+    removed: &[bool],
+    start: usize,
+    from: Option<usize>,
+) -> Vec<(usize, Option<usize>)> {
+    let mut order: Vec<(usize, Option<usize>)> = vec![(start, from)];
+    let mut i = 0;
+
+    while i < order.len() {
+        let (id, from) = order[i];
+        for &next in neighbours[id].iter().flatten() {
+            if Some(next) != from && !removed[next] {
+                order.push((next, Some(id)));
+            }
+        }
+        i += 1;
+    }
+
+    order
+}
+
+/// Returns a centroid of the component of `start`: a node whose removal leaves
+/// parts with at most half of the nodes of the component.
+fn find_centroid(
+    neighbours: &[[Option<usize>; 3]],
+    removed: &[bool],
+    size: &mut [usize],
+    start: usize,
+) -> usize {
+    let order = component(neighbours, removed, start, None);
+    let total = order.len();
+
+    // Children come after their parent in visit order, so sizes are computed backwards
+    for &(id, _) in &order {
+        size[id] = 1;
+    }
+    for &(id, from) in order.iter().rev() {
+        if let Some(from) = from {
+            size[from] += size[id];
+        }
+    }
+
+    // Walks towards the largest part until no part is bigger than half of the component
+    let mut centroid = start;
+    let mut from: Option<usize> = None;
+    loop {
+        let heavy = neighbours[centroid]
+            .iter()
+            .flatten()
+            .copied()
+            .find(|&next| Some(next) != from && !removed[next] && size[next] * 2 > total);
+
+        match heavy {
+            Some(next) => {
+                from = Some(centroid);
+                centroid = next;
+            }
+            None => return centroid,
+        }
+    }
+}
+
+/// Returns the sums of the keys on the paths from `start` to every node of its
+/// component, which is entered from the node `from`.
+fn path_sums(
+    tree: &Tree,
+    neighbours: &[[Option<usize>; 3]],
+    removed: &[bool],
+    start: usize,
+    from: usize,
+) -> Vec<i64> {
+    let order = component(neighbours, removed, start, Some(from));
+    let mut sums: HashMap<usize, i64> = HashMap::with_capacity(order.len());
+
+    for &(id, from) in &order {
+        let before = if id == start { 0 } else { sums[&from.unwrap()] };
+        sums.insert(id, before + tree.nodes[id].key as i64);
+    }
+
+    order.iter().map(|(id, _)| sums[id]).collect()
+}
+
+#[cfg(test)]
+mod path_sum_tests {
+    use super::*;
+    use crate::oracle::{parents, subtree};
+    use crate::random::{random_chain, random_tree, Rng};
+
+    /// Returns the sum of the keys on the path between `a` and `b`, as `i64`.
+    fn path_sum(tree: &Tree, parents: &[Option<usize>], a: usize, b: usize) -> i64 {
+        crate::oracle::path_sum(tree, parents, a, b) as i64
+    }
+
+    /// Counts the downward paths by enumerating every node and each of its descendants.
+    fn brute_downward(tree: &Tree, target: i64) -> u64 {
+        let parents = parents(tree);
+        let mut count = 0;
+
+        for top in 0..tree.nodes.len() {
+            for bottom in subtree(tree, Some(top)) {
+                if path_sum(tree, &parents, top, bottom) == target {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Counts the paths by enumerating every unordered pair of nodes.
+    fn brute_all(tree: &Tree, target: i64) -> u64 {
+        let parents = parents(tree);
+        let n = tree.nodes.len();
+        let mut count = 0;
+
+        for a in 0..n {
+            for b in a..n {
+                if path_sum(tree, &parents, a, b) == target {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn downward_paths() {
+        let mut tree = Tree::with_root(10);
+
+        //          10
+        //        /    \
+        //       5     -3
+        //      / \      \
+        //     3   2     11
+        //    / \   \
+        //   3  -2   1
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, -3, false); // id 2
+        tree.add_node(1, 3, true); // id 3
+        tree.add_node(1, 2, false); // id 4
+        tree.add_node(2, 11, false); // id 5
+        tree.add_node(3, 3, true); // id 6
+        tree.add_node(3, -2, false); // id 7
+        tree.add_node(4, 1, false); // id 8
+
+        assert_eq!(tree.count_downward_paths_with_sum(8), 3);
+        assert_eq!(tree.count_downward_paths_with_sum(8), brute_downward(&tree, 8));
+    }
+
+    #[test]
+    fn all_paths() {
+        let mut tree = Tree::with_root(1);
+
+        //       1
+        //     /   \
+        //    2     3
+        //   /
+        //  4
+        tree.add_node(0, 2, true); // id 1
+        tree.add_node(0, 3, false); // id 2
+        tree.add_node(1, 4, true); // id 3
+
+        // {3}, {1, 2}
+        assert_eq!(tree.count_all_paths_with_sum(3), 2);
+        // {4, 2}, {2, 1, 3}
+        assert_eq!(tree.count_all_paths_with_sum(6), 2);
+        // {4, 2, 1, 3}
+        assert_eq!(tree.count_all_paths_with_sum(10), 1);
+        assert_eq!(tree.count_all_paths_with_sum(100), 0);
+    }
+
+    #[test]
+    fn single_node() {
+        let tree = Tree::with_root(5);
+
+        assert_eq!(tree.count_downward_paths_with_sum(5), 1);
+        assert_eq!(tree.count_all_paths_with_sum(5), 1);
+        assert_eq!(tree.count_all_paths_with_sum(0), 0);
+    }
+
+    #[test]
+    fn random_trees() {
+        let mut rng = Rng::new(29);
+
+        for _ in 0..1000 {
+            let n = 1 + rng.below(30);
+            let tree = random_tree(&mut rng, n, -5..=5);
+            let target = rng.key(-8..=8) as i64;
+
+            assert_eq!(tree.count_downward_paths_with_sum(target), brute_downward(&tree, target));
+            assert_eq!(tree.count_all_paths_with_sum(target), brute_all(&tree, target));
+        }
+    }
+
+    #[test]
+    fn random_chains() {
+        let mut rng = Rng::new(30);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(40);
+            let tree = random_chain(&mut rng, n, -3..=3);
+            let target = rng.key(-4..=4) as i64;
+
+            assert_eq!(tree.count_downward_paths_with_sum(target), brute_downward(&tree, target));
+            assert_eq!(tree.count_all_paths_with_sum(target), brute_all(&tree, target));
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use crate::max_path_step;
+
+/// A handle to a version of a `PersistentTree`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Version(usize);
+
+/// A node of the shared pool. Children are pool indices, `id` is the node id
+/// seen by the user, which is the same in every version containing the node.
+struct PoolNode {
+    key: i32,
+    id: usize,
+    id_left: Option<usize>,
+    id_right: Option<usize>,
+}
+
+/// A binary tree that keeps all its versions, using path copying.
+///
+/// `add_node` and `set_key` never modify a version: they copy the O(h) nodes on the
+/// path from the root to the changed node and return a new version, which shares
+/// every other node with the version it comes from. Any version, old or new, can be
+/// queried and can be the base of further edits.
+pub struct PersistentTree {
+    pool: Vec<PoolNode>,
+    /// Pool index of the root and number of nodes of every version
+    versions: Vec<(usize, usize)>,
+    /// Parent id and side (`true` for left) of every node id. The position of a node
+    /// never changes, so the same path leads to it in every version containing it.
+    links: Vec<Option<(usize, bool)>>,
+}
+
+impl PersistentTree {
+    /// Returns a tree whose first version has a single node, the root with id 0.
+    pub fn with_root(key: i32) -> Self {
+        Self {
+            pool: vec![PoolNode {
+                key,
+                id: 0,
+                id_left: None,
+                id_right: None,
+            }],
+            versions: vec![(0, 1)],
+            links: vec![None],
+        }
+    }
+
+    /// Returns the first version, made of the root only.
+    pub fn first(&self) -> Version {
+        Version(0)
+    }
+
+    /// Returns the last version created.
+    pub fn latest(&self) -> Version {
+        Version(self.versions.len() - 1)
+    }
+
+    /// Returns the number of versions created so far.
+    pub fn versions(&self) -> usize {
+        self.versions.len()
+    }
+
+    /// Returns the number of nodes in `version`.
+    pub fn len(&self, version: Version) -> usize {
+        self.versions[version.0].1
+    }
+
+    /// Returns the key of the node `id` in `version`, or `None` if the node
+    /// doesn't belong to `version`.
+    pub fn key(&self, version: Version, id: usize) -> Option<i32> {
+        self.path_to(version, id)
+            .map(|path| self.pool[path[path.len() - 1]].key)
+    }
+
+    /// Adds a child to the node with `parent_id` of `version`, and returns the new
+    /// version with the id of the new node. The new node has the specified `key`. It
+    /// is the left child of the node `parent_id` iff `is_left` is `true`, the right
+    /// child otherwise. Node ids are unique across all versions.
+    ///
+    /// # Panics
+    /// Panics if the `parent_id` does not exist in `version`, or if the node
+    /// `parent_id` has the child already set.
+    pub fn add_node(
+        &mut self,
+        version: Version,
+        parent_id: usize,
+        key: i32,
+        is_left: bool,
+    ) -> (Version, usize) {
+        let path = self
+            .path_to(version, parent_id)
+            .expect("Parent node id does not exist");
+
+        let parent = &self.pool[path[path.len() - 1]];
+        if is_left {
+            assert!(
+                parent.id_left.is_none(),
+                "Parent node has the left child already set"
+            );
+        } else {
+            assert!(
+                parent.id_right.is_none(),
+                "Parent node has the right child already set"
+            );
+        }
+
+        let child_id = self.links.len();
+        self.links.push(Some((parent_id, is_left)));
+
+        self.pool.push(PoolNode {
+            key,
+            id: child_id,
+            id_left: None,
+            id_right: None,
+        });
+        let child = self.pool.len() - 1;
+
+        // Copies the parent with the new child, then the rest of the path
+        let parent = &self.pool[path[path.len() - 1]];
+        let (id_left, id_right) = if is_left {
+            (Some(child), parent.id_right)
+        } else {
+            (parent.id_left, Some(child))
+        };
+        let copy = self.copy_node(path[path.len() - 1], parent.key, id_left, id_right);
+
+        let root = self.copy_path(&path[..path.len() - 1], copy);
+        let len = self.versions[version.0].1 + 1;
+        self.versions.push((root, len));
+
+        (self.latest(), child_id)
+    }
+
+    /// Sets the key of the node `id` of `version` to `key` and returns the new version.
+    ///
+    /// # Panics
+    /// Panics if the node `id` does not exist in `version`.
+    pub fn set_key(&mut self, version: Version, id: usize, key: i32) -> Version {
+        let path = self.path_to(version, id).expect("Node id does not exist");
+
+        let node = &self.pool[path[path.len() - 1]];
+        let copy = self.copy_node(path[path.len() - 1], key, node.id_left, node.id_right);
+
+        let root = self.copy_path(&path[..path.len() - 1], copy);
+        let len = self.versions[version.0].1;
+        self.versions.push((root, len));
+
+        self.latest()
+    }
+
+    /// Returns the sum of all the keys in `version`
+    pub fn sum(&self, version: Version) -> i32 {
+        self.postorder(version)
+            .iter()
+            .map(|&index| self.pool[index].key)
+            .sum()
+    }
+
+    /// Checks if `version` is a Binary Search Tree, with an iterative in-order
+    /// visit: the keys must come out in non-decreasing order.
+    pub fn is_bst(&self, version: Version) -> bool {
+        let mut stack: Vec<usize> = Vec::new();
+        let mut current = Some(self.versions[version.0].0);
+        let mut previous: Option<i32> = None;
+
+        loop {
+            while let Some(index) = current {
+                stack.push(index);
+                current = self.pool[index].id_left;
+            }
+
+            let Some(index) = stack.pop() else {
+                return true;
+            };
+
+            let key = self.pool[index].key;
+            if previous.is_some_and(|previous| previous > key) {
+                return false;
+            }
+
+            previous = Some(key);
+            current = self.pool[index].id_right;
+        }
+    }
+
+    /// Returns the maximum path sum between two leaves in `version`.
+    /// If no path is found then it returns None.
+    pub fn max_path_sum(&self, version: Version) -> Option<i32> {
+        // Results of `max_path_step` of the subtrees whose parent is still to be
+        // visited: in post-order the right one is on top of the left one.
+        let mut results: Vec<(Option<i32>, Option<i32>)> = Vec::new();
+
+        for index in self.postorder(version) {
+            let node = &self.pool[index];
+            let right = node.id_right.map_or((None, None), |_| results.pop().unwrap());
+            let left = node.id_left.map_or((None, None), |_| results.pop().unwrap());
+
+            results.push(max_path_step(node.key, left, right));
+        }
+
+        results[0].1
+    }
+
+    /// Returns the pool indices of the nodes on the path from the root of `version`
+    /// to the node `id`, or `None` if the node doesn't belong to `version`.
+    fn path_to(&self, version: Version, id: usize) -> Option<Vec<usize>> {
+        assert!(version.0 < self.versions.len(), "Version does not exist");
+
+        // Sides of the moves from the root to the node
+        let mut sides: Vec<bool> = Vec::new();
+        let mut current = *self.links.get(id)?;
+        while let Some((parent_id, is_left)) = current {
+            sides.push(is_left);
+            current = self.links[parent_id];
+        }
+
+        let mut path = vec![self.versions[version.0].0];
+        for is_left in sides.into_iter().rev() {
+            let node = &self.pool[path[path.len() - 1]];
+            let child = if is_left { node.id_left } else { node.id_right };
+            path.push(child?);
+        }
+
+        (self.pool[path[path.len() - 1]].id == id).then_some(path)
+    }
+
+    /// Pushes a copy of the node at pool index `index` with the given key and
+    /// children and returns its pool index.
+    fn copy_node(
+        &mut self,
+        index: usize,
+        key: i32,
+        id_left: Option<usize>,
+        id_right: Option<usize>,
+    ) -> usize {
+        let id = self.pool[index].id;
+        self.pool.push(PoolNode {
+            key,
+            id,
+            id_left,
+            id_right,
+        });
+
+        self.pool.len() - 1
+    }
+
+    /// Copies the nodes of `path`, from the deepest one, replacing each time the
+    /// child on the path with the copy just made. Returns the pool index of the new root.
+    fn copy_path(&mut self, path: &[usize], mut copy: usize) -> usize {
+        for &index in path.iter().rev() {
+            let node = &self.pool[index];
+            let copied_id = self.pool[copy].id;
+
+            let (id_left, id_right) = match node.id_left {
+                Some(left) if self.pool[left].id == copied_id => (Some(copy), node.id_right),
+                _ => (node.id_left, Some(copy)),
+            };
+
+            copy = self.copy_node(index, node.key, id_left, id_right);
+        }
+
+        copy
+    }
+
+    /// Returns the pool indices of the nodes of `version` in post-order.
+    fn postorder(&self, version: Version) -> Vec<usize> {
+        assert!(version.0 < self.versions.len(), "Version does not exist");
+
+        let (root, len) = self.versions[version.0];
+        let mut order: Vec<usize> = Vec::with_capacity(len);
+        let mut stack: Vec<usize> = vec![root];
+
+        // Visits node, right, left and then reverses the result
+        while let Some(index) = stack.pop() {
+            order.push(index);
+
+            let node = &self.pool[index];
+            stack.extend(node.id_left);
+            stack.extend(node.id_right);
+        }
+
+        order.reverse();
+        order
+    }
+}
+
+#[cfg(test)]
+mod persistent_tests {
+    use super::*;
+    use crate::random::Rng;
+    use crate::Tree;
+
+    #[test]
+    fn old_versions_stay_queryable() {
+        let mut tree = PersistentTree::with_root(10);
+        let v0 = tree.first();
+
+        let (v1, a) = tree.add_node(v0, 0, 5, true); // id 1
+        let (v2, b) = tree.add_node(v1, 0, 22, false); // id 2
+        let v3 = tree.set_key(v2, a, 30);
+
+        assert_eq!(tree.versions(), 4);
+        assert_eq!(tree.latest(), v3);
+
+        assert_eq!(tree.sum(v0), 10);
+        assert_eq!(tree.sum(v1), 15);
+        assert_eq!(tree.sum(v2), 37);
+        assert_eq!(tree.sum(v3), 62);
+
+        assert!(tree.is_bst(v2));
+        assert!(!tree.is_bst(v3));
+
+        assert_eq!(tree.max_path_sum(v1), None);
+        assert_eq!(tree.max_path_sum(v2), Some(37));
+        assert_eq!(tree.max_path_sum(v3), Some(62));
+
+        assert_eq!(tree.key(v0, a), None);
+        assert_eq!(tree.key(v1, a), Some(5));
+        assert_eq!(tree.key(v3, a), Some(30));
+        assert_eq!(tree.key(v1, b), None);
+        assert_eq!(tree.len(v1), 2);
+        assert_eq!(tree.len(v3), 3);
+    }
+
+    #[test]
+    fn branching_versions() {
+        let mut tree = PersistentTree::with_root(10);
+        let v0 = tree.first();
+
+        // Two different children added to the same version
+        let (left, a) = tree.add_node(v0, 0, 5, true); // id 1
+        let (right, b) = tree.add_node(v0, 0, 15, false); // id 2
+
+        assert_eq!(tree.key(left, a), Some(5));
+        assert_eq!(tree.key(left, b), None);
+        assert_eq!(tree.key(right, a), None);
+        assert_eq!(tree.key(right, b), Some(15));
+
+        // The id of a node is never reused, not even in other branches
+        let (both, c) = tree.add_node(left, 0, 15, false);
+        assert_eq!(c, 3);
+        assert_eq!(tree.key(both, b), None);
+        assert_eq!(tree.sum(both), 30);
+    }
+
+    #[test]
+    #[should_panic(expected = "Parent node has the left child already set")]
+    fn child_already_set() {
+        let mut tree = PersistentTree::with_root(10);
+        let (v1, _) = tree.add_node(tree.first(), 0, 5, true);
+
+        tree.add_node(v1, 0, 3, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Node id does not exist")]
+    fn node_missing_in_version() {
+        let mut tree = PersistentTree::with_root(10);
+        let (_, a) = tree.add_node(tree.first(), 0, 5, true);
+
+        tree.set_key(tree.first(), a, 3);
+    }
+
+    #[test]
+    fn unchanged_nodes_are_shared() {
+        let mut tree = PersistentTree::with_root(0);
+        let mut version = tree.first();
+        let mut last = 0;
+
+        // A chain of 100 nodes, then a key change at the bottom
+        for i in 1..100 {
+            (version, last) = tree.add_node(version, last, i, false);
+        }
+        let before = tree.pool.len();
+        tree.set_key(version, last, -1);
+
+        // Only the path to the changed node is copied
+        assert_eq!(tree.pool.len() - before, 100);
+
+        let before = tree.pool.len();
+        tree.set_key(version, 0, -1);
+        assert_eq!(tree.pool.len() - before, 1);
+    }
+
+    #[test]
+    fn random_versions_match_replay() {
+        let mut rng = Rng::new(33);
+
+        for _ in 0..100 {
+            let mut tree = PersistentTree::with_root(rng.key(-50..=50));
+
+            // For every version, the operations to replay on a `Tree`. The node ids
+            // of a `Tree` are assigned in order, so every version keeps its own mapping.
+            let mut histories: Vec<Vec<(usize, i32, Option<bool>)>> = vec![Vec::new()];
+            let mut ids: Vec<Vec<usize>> = vec![vec![0]];
+
+            for _ in 0..40 {
+                let base = rng.below(tree.versions());
+                let version = Version(base);
+                let id = ids[base][rng.below(ids[base].len())];
+                let key = rng.key(-50..=50);
+
+                let mut history = histories[base].clone();
+                let mut version_ids = ids[base].clone();
+
+                if rng.coin() {
+                    tree.set_key(version, id, key);
+                    history.push((id, key, None));
+                } else {
+                    let is_left = rng.coin();
+                    let path = tree.path_to(version, id).unwrap();
+                    let node = &tree.pool[path[path.len() - 1]];
+                    if (is_left && node.id_left.is_some()) || (!is_left && node.id_right.is_some()) {
+                        continue;
+                    }
+
+                    let (_, child) = tree.add_node(version, id, key, is_left);
+                    history.push((id, key, Some(is_left)));
+                    version_ids.push(child);
+                }
+
+                histories.push(history);
+                ids.push(version_ids);
+            }
+
+            for (v, history) in histories.iter().enumerate() {
+                let version = Version(v);
+
+                // Replays the operations, mapping the persistent ids to `Tree` ids
+                let mut replay = Tree::with_root(tree.key(tree.first(), 0).unwrap());
+                let mut tree_ids = std::collections::HashMap::from([(0, 0)]);
+                let mut next_id = 1;
+                for &(id, key, add) in history {
+                    match add {
+                        None => replay.set_key(tree_ids[&id], key),
+                        Some(is_left) => {
+                            let child = replay.add_node(tree_ids[&id], key, is_left);
+                            tree_ids.insert(ids[v][next_id], child);
+                            next_id += 1;
+                        }
+                    }
+                }
+
+                assert_eq!(tree.len(version), replay.nodes.len());
+                assert_eq!(tree.sum(version), replay.sum());
+                assert_eq!(tree.is_bst(version), replay.is_bst());
+                assert_eq!(tree.max_path_sum(version), replay.max_path_sum());
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Seeded generators of random trees, meant for property testing.
+//!
+//! The generators only depend on the standard library: the same seed always
+//! produces the same sequence of trees.
+
+use crate::Tree;
+use std::ops::RangeInclusive;
+
+/// A small pseudo-random number generator (SplitMix64).
+/// It is not suitable for cryptography, only for generating test inputs.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next pseudo-random 64 bits value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..bound`.
+    ///
+    /// # Panics
+    /// Panics if `bound` is 0.
+    pub fn below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "The bound must be positive");
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns a value in the inclusive range `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is empty.
+    pub fn key(&mut self, range: RangeInclusive<i32>) -> i32 {
+        let (low, high) = (*range.start() as i64, *range.end() as i64);
+        assert!(low <= high, "The range of keys is empty");
+
+        let width = (high - low + 1) as u64;
+        (low + (self.next_u64() % width) as i64) as i32
+    }
+
+    /// Returns `true` or `false` with the same probability.
+    pub fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}
+
+/// Returns a tree with `n` nodes of arbitrary shape, with keys drawn from `keys`.
+/// Every new node is attached to a free child slot chosen uniformly at random.
+///
+/// # Panics
+/// Panics if `n` is 0.
+pub fn random_tree(rng: &mut Rng, n: usize, keys: RangeInclusive<i32>) -> Tree {
+    assert!(n > 0, "A tree has at least one node");
+
+    let mut tree = Tree::with_root(rng.key(keys.clone()));
+    let mut free_slots: Vec<(usize, bool)> = vec![(0, true), (0, false)];
+
+    for _ in 1..n {
+        let (parent_id, is_left) = free_slots.swap_remove(rng.below(free_slots.len()));
+        let id = tree.add_node(parent_id, rng.key(keys.clone()), is_left);
+
+        free_slots.push((id, true));
+        free_slots.push((id, false));
+    }
+
+    tree
+}
+
+/// Returns a valid Binary Search Tree with `n` nodes, with keys drawn from `keys`.
+/// The keys are inserted in random order, so the shape is random as well.
+///
+/// # Panics
+/// Panics if `n` is 0.
+pub fn random_bst(rng: &mut Rng, n: usize, keys: RangeInclusive<i32>) -> Tree {
+    assert!(n > 0, "A tree has at least one node");
+
+    let mut tree = Tree::with_root(rng.key(keys.clone()));
+
+    for _ in 1..n {
+        let key = rng.key(keys.clone());
+        let mut id = tree.root;
+
+        // Standard BST insertion, equal keys go to the right
+        loop {
+            let node = &tree.nodes[id];
+            let is_left = key < node.key;
+            let child = if is_left { node.id_left } else { node.id_right };
+
+            match child {
+                Some(child) => id = child,
+                None => {
+                    tree.add_node(id, key, is_left);
+                    break;
+                }
+            }
+        }
+    }
+
+    tree
+}
+
+/// Returns a degenerate tree with `n` nodes where every node has at most one
+/// child, on a random side. Keys are drawn from `keys`.
+///
+/// # Panics
+/// Panics if `n` is 0.
+pub fn random_chain(rng: &mut Rng, n: usize, keys: RangeInclusive<i32>) -> Tree {
+    assert!(n > 0, "A tree has at least one node");
+
+    let mut tree = Tree::with_root(rng.key(keys.clone()));
+    let mut last = 0;
+
+    for _ in 1..n {
+        last = tree.add_node(last, rng.key(keys.clone()), rng.coin());
+    }
+
+    tree
+}
@@ -0,0 +1,430 @@
+use crate::{Node, Tree};
+use std::io::{self, Read, Write};
+use std::sync::OnceLock;
+
+const MAGIC: &[u8; 4] = b"CPBT";
+const FORMAT_VERSION: u8 = 1;
+
+/// Lookup table of the CRC-32 (IEEE 802.3) polynomial, built at compile time.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Updates the running CRC-32 `crc` (not yet finalised) with `bytes`.
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        crc = CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// A writer that computes the CRC-32 of everything written through it.
+struct CrcWriter<'a, W: Write> {
+    inner: &'a mut W,
+    crc: u32,
+}
+
+impl<W: Write> CrcWriter<'_, W> {
+    fn write_varint(&mut self, mut value: u64) -> io::Result<()> {
+        let mut buf = [0u8; 10];
+        let mut len = 0;
+
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+
+        self.write_all(&buf[..len])
+    }
+}
+
+impl<W: Write> Write for CrcWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc = crc32_update(self.crc, &buf[..written]);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that computes the CRC-32 of everything read through it.
+struct CrcReader<'a, R: Read> {
+    inner: &'a mut R,
+    crc: u32,
+}
+
+impl<R: Read> CrcReader<'_, R> {
+    fn read_byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0u8; 1];
+        self.read_exact(&mut byte)?;
+
+        Ok(byte[0])
+    }
+
+    fn read_varint(&mut self) -> io::Result<u64> {
+        let mut value: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            let bits = (byte & 0x7F) as u64;
+
+            if shift == 63 && bits > 1 {
+                return Err(invalid("varint is too long"));
+            }
+
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(invalid("varint is too long"))
+    }
+
+    /// Reads an optional child id, stored as id + 1 with 0 for a missing child.
+    fn read_child(&mut self, count: usize) -> io::Result<Option<usize>> {
+        match self.read_varint()? {
+            0 => Ok(None),
+            id if id <= count as u64 => Ok(Some(id as usize - 1)),
+            _ => Err(invalid("child id is out of range")),
+        }
+    }
+}
+
+impl<R: Read> Read for CrcReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc = crc32_update(self.crc, &buf[..read]);
+
+        Ok(read)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn zigzag(key: i32) -> u64 {
+    ((key << 1) ^ (key >> 31)) as u32 as u64
+}
+
+fn unzigzag(value: u64) -> io::Result<i32> {
+    let value = u32::try_from(value).map_err(|_| invalid("key is out of range"))?;
+    Ok(((value >> 1) as i32) ^ -((value & 1) as i32))
+}
+
+impl Tree {
+    /// Writes the tree to `writer` in a compact binary format. Node ids are
+    /// preserved, cached aggregates aren't saved.
+    ///
+    /// ```text
+    /// magic    4 bytes  "CPBT"
+    /// version  1 byte   format version, currently 1
+    /// count    varint   number of nodes, at least 1
+    /// root     varint   id of the root
+    /// nodes    count times: key as zigzag varint, left and right as varint (id + 1, 0 if missing)
+    /// checksum 4 bytes  CRC-32 of all the previous bytes, little endian
+    /// ```
+    ///
+    /// Varints are LEB128: 7 bits per byte, least significant group first.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut writer = CrcWriter {
+            inner: writer,
+            crc: !0,
+        };
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_varint(self.nodes.len() as u64)?;
+        writer.write_varint(self.root as u64)?;
+
+        let child = |id: Option<usize>| id.map_or(0, |id| id as u64 + 1);
+        for node in &self.nodes {
+            writer.write_varint(zigzag(node.key))?;
+            writer.write_varint(child(node.id_left))?;
+            writer.write_varint(child(node.id_right))?;
+        }
+
+        let checksum = !writer.crc;
+        writer.inner.write_all(&checksum.to_le_bytes())
+    }
+
+    /// Reads a tree written by `write_to`.
+    ///
+    /// # Errors
+    /// Returns an error of kind `InvalidData` if the data is corrupted: wrong magic,
+    /// unknown format version, checksum mismatch, child ids out of range, or links
+    /// that don't form a single tree rooted at the root. Returns the errors of
+    /// `reader`, such as `UnexpectedEof` on truncated data.
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Tree> {
+        let mut reader = CrcReader {
+            inner: reader,
+            crc: !0,
+        };
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a serialized tree"));
+        }
+        if reader.read_byte()? != FORMAT_VERSION {
+            return Err(invalid("unsupported format version"));
+        }
+
+        let count = usize::try_from(reader.read_varint()?)
+            .map_err(|_| invalid("node count is too big"))?;
+        if count == 0 {
+            return Err(invalid("a tree has at least one node"));
+        }
+
+        let root = reader.read_varint()?;
+        if root >= count as u64 {
+            return Err(invalid("root id is out of range"));
+        }
+        let root = root as usize;
+
+        // The count is not trusted yet, so the arena grows as nodes are actually read
+        let mut nodes: Vec<Node> = Vec::with_capacity(count.min(1 << 16));
+        for _ in 0..count {
+            let key = unzigzag(reader.read_varint()?)?;
+            let mut node = Node::new(key);
+            node.id_left = reader.read_child(count)?;
+            node.id_right = reader.read_child(count)?;
+
+            nodes.push(node);
+        }
+
+        let computed = !reader.crc;
+        let mut checksum = [0u8; 4];
+        reader.inner.read_exact(&mut checksum)?;
+        if u32::from_le_bytes(checksum) != computed {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        let mut tree = Tree {
+            nodes,
+            root,
+            cache: None,
+            parents: OnceLock::new(),
+        };
+        let parents = tree.link_parents()?;
+        tree.parents = OnceLock::from(parents);
+
+        Ok(tree)
+    }
+
+    /// Returns the parent links from the child links, checking that they form a
+    /// single tree: every node but the root has exactly one parent, and every
+    /// node is reachable from the root.
+    fn link_parents(&self) -> io::Result<Vec<Option<usize>>> {
+        let mut parents: Vec<Option<usize>> = vec![None; self.nodes.len()];
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            for child in [node.id_left, node.id_right].into_iter().flatten() {
+                if child == self.root || parents[child].is_some() {
+                    return Err(invalid("node has more than one parent"));
+                }
+                parents[child] = Some(id);
+            }
+        }
+
+        // With one parent per node, a node is unreachable iff it lies on a cycle
+        let mut reached = 0;
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            reached += 1;
+
+            let node = &self.nodes[id];
+            stack.extend(node.id_left);
+            stack.extend(node.id_right);
+        }
+
+        if reached != self.nodes.len() {
+            return Err(invalid("nodes are not reachable from the root"));
+        }
+
+        Ok(parents)
+    }
+}
+
+#[cfg(test)]
+mod serialize_tests {
+    use super::*;
+    use crate::random::{random_tree, Rng};
+
+    /// Encodes arbitrary links, even invalid ones, with a valid checksum.
+    fn encode_raw(root: u64, nodes: &[(i32, u64, u64)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer = CrcWriter {
+            inner: &mut bytes,
+            crc: !0,
+        };
+
+        writer.write_all(MAGIC).unwrap();
+        writer.write_all(&[FORMAT_VERSION]).unwrap();
+        writer.write_varint(nodes.len() as u64).unwrap();
+        writer.write_varint(root).unwrap();
+        for &(key, left, right) in nodes {
+            writer.write_varint(zigzag(key)).unwrap();
+            writer.write_varint(left).unwrap();
+            writer.write_varint(right).unwrap();
+        }
+
+        let checksum = !writer.crc;
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    fn encode(tree: &Tree) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn assert_same_tree(a: &Tree, b: &Tree) {
+        assert_eq!(a.root, b.root);
+        assert_eq!(a.nodes.len(), b.nodes.len());
+
+        for (x, y) in a.nodes.iter().zip(&b.nodes) {
+            assert_eq!(x.key, y.key);
+            assert_eq!(x.id_left, y.id_left);
+            assert_eq!(x.id_right, y.id_right);
+        }
+        assert_eq!(a.parents(), b.parents());
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(!crc32_update(!0, b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn zigzag_keys() {
+        for key in [0, 1, -1, 2, -2, i32::MAX, i32::MIN] {
+            assert_eq!(unzigzag(zigzag(key)).unwrap(), key);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut tree = Tree::with_root(i32::MIN);
+
+        tree.add_node(0, i32::MAX, true); // id 1
+        tree.add_node(0, -5, false); // id 2
+        tree.add_node(2, 300, true); // id 3
+        tree.rotate_left(0);
+
+        let bytes = encode(&tree);
+        let read = Tree::read_from(&mut bytes.as_slice()).unwrap();
+
+        assert_same_tree(&tree, &read);
+        assert_eq!(read.root(), 2);
+    }
+
+    #[test]
+    fn random_round_trips() {
+        let mut rng = Rng::new(34);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(200);
+            let tree = random_tree(&mut rng, n, i32::MIN..=i32::MAX);
+
+            let bytes = encode(&tree);
+            let read = Tree::read_from(&mut bytes.as_slice()).unwrap();
+
+            assert_same_tree(&tree, &read);
+        }
+    }
+
+    #[test]
+    fn corrupted_bytes() {
+        let mut tree = Tree::with_root(10);
+        tree.add_node(0, 5, true);
+        tree.add_node(0, 200, false);
+        tree.add_node(1, -7, false);
+
+        let bytes = encode(&tree);
+
+        // Any single changed byte is detected, by the checksum if nothing else
+        for i in 0..bytes.len() {
+            for flip in [0x01, 0x80, 0xFF] {
+                let mut corrupted = bytes.clone();
+                corrupted[i] ^= flip;
+
+                assert!(Tree::read_from(&mut corrupted.as_slice()).is_err());
+            }
+        }
+
+        // Truncated data
+        for len in 0..bytes.len() {
+            assert!(Tree::read_from(&mut &bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn invalid_structures() {
+        let error = |bytes: Vec<u8>| Tree::read_from(&mut bytes.as_slice()).err().unwrap();
+
+        // No nodes
+        assert_eq!(error(encode_raw(0, &[])).kind(), io::ErrorKind::InvalidData);
+
+        // Root out of range
+        assert_eq!(error(encode_raw(1, &[(1, 0, 0)])).kind(), io::ErrorKind::InvalidData);
+
+        // Child out of range
+        assert_eq!(error(encode_raw(0, &[(1, 2, 0)])).kind(), io::ErrorKind::InvalidData);
+
+        // Two parents for the node 2
+        let bytes = encode_raw(0, &[(1, 2, 3), (2, 3, 0), (3, 0, 0)]);
+        assert_eq!(error(bytes).to_string(), "node has more than one parent");
+
+        // The root as a child
+        let bytes = encode_raw(0, &[(1, 2, 0), (2, 1, 0)]);
+        assert_eq!(error(bytes).to_string(), "node has more than one parent");
+
+        // Two separate trees
+        let bytes = encode_raw(0, &[(1, 0, 0), (2, 0, 0)]);
+        assert_eq!(error(bytes).to_string(), "nodes are not reachable from the root");
+
+        // A cycle detached from the root
+        let bytes = encode_raw(0, &[(1, 0, 0), (2, 3, 0), (3, 2, 0)]);
+        assert_eq!(error(bytes).to_string(), "nodes are not reachable from the root");
+
+        // A valid one
+        let bytes = encode_raw(1, &[(1, 0, 0), (2, 1, 3), (3, 0, 0)]);
+        let tree = Tree::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(tree.sum(), 6);
+        assert_eq!(tree.max_path_sum(), Some(6));
+    }
+}
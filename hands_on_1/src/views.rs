@@ -0,0 +1,220 @@
+use crate::Tree;
+use std::collections::{BTreeMap, VecDeque};
+
+/// A node reached by the breadth-first visit, with its level (0 for the root) and
+/// its horizontal distance from the root (-1 for a left move, +1 for a right move).
+struct Visited {
+    id: usize,
+    level: usize,
+    distance: i64,
+}
+
+impl Tree {
+    /// Visits the tree level by level, from left to right, with an explicit queue.
+    fn bfs(&self) -> Vec<Visited> {
+        let mut order: Vec<Visited> = Vec::with_capacity(self.nodes.len());
+        let mut queue: VecDeque<Visited> = VecDeque::new();
+        queue.push_back(Visited {
+            id: self.root,
+            level: 0,
+            distance: 0,
+        });
+
+        while let Some(visited) = queue.pop_front() {
+            let node = &self.nodes[visited.id];
+
+            if let Some(id_left) = node.id_left {
+                queue.push_back(Visited {
+                    id: id_left,
+                    level: visited.level + 1,
+                    distance: visited.distance - 1,
+                });
+            }
+            if let Some(id_right) = node.id_right {
+                queue.push_back(Visited {
+                    id: id_right,
+                    level: visited.level + 1,
+                    distance: visited.distance + 1,
+                });
+            }
+
+            order.push(visited);
+        }
+
+        order
+    }
+
+    /// Returns the keys of the tree level by level, every level from left to right.
+    fn levels(&self) -> Vec<Vec<i32>> {
+        let mut levels: Vec<Vec<i32>> = Vec::new();
+
+        for visited in self.bfs() {
+            if visited.level == levels.len() {
+                levels.push(Vec::new());
+            }
+            levels[visited.level].push(self.nodes[visited.id].key);
+        }
+
+        levels
+    }
+
+    /// Returns the keys of the nodes grouped by horizontal distance from the root,
+    /// from the leftmost column. Every column lists its nodes level by level.
+    fn columns(&self) -> BTreeMap<i64, Vec<i32>> {
+        let mut columns: BTreeMap<i64, Vec<i32>> = BTreeMap::new();
+
+        for visited in self.bfs() {
+            columns
+                .entry(visited.distance)
+                .or_default()
+                .push(self.nodes[visited.id].key);
+        }
+
+        columns
+    }
+
+    /// Returns the keys visible from the left side: the first node of every level.
+    pub fn left_view(&self) -> Vec<i32> {
+        self.levels().iter().map(|level| level[0]).collect()
+    }
+
+    /// Returns the keys visible from the right side: the last node of every level.
+    pub fn right_view(&self) -> Vec<i32> {
+        self.levels()
+            .iter()
+            .map(|level| level[level.len() - 1])
+            .collect()
+    }
+
+    /// Returns the keys visible from above: the highest node of every column,
+    /// from the leftmost column. Ties are won by the leftmost node.
+    pub fn top_view(&self) -> Vec<i32> {
+        self.columns().values().map(|column| column[0]).collect()
+    }
+
+    /// Returns the keys visible from below: the lowest node of every column,
+    /// from the leftmost column. Ties are won by the rightmost node.
+    pub fn bottom_view(&self) -> Vec<i32> {
+        self.columns()
+            .values()
+            .map(|column| column[column.len() - 1])
+            .collect()
+    }
+
+    /// Returns the keys grouped by horizontal distance from the root, from the
+    /// leftmost column. Every column lists its nodes level by level, left to right.
+    pub fn vertical_order(&self) -> Vec<Vec<i32>> {
+        self.columns().into_values().collect()
+    }
+
+    /// Returns the keys level by level, alternating the direction: the root level
+    /// from left to right, the next one from right to left and so on.
+    pub fn zigzag_level_order(&self) -> Vec<Vec<i32>> {
+        let mut levels = self.levels();
+
+        for level in levels.iter_mut().skip(1).step_by(2) {
+            level.reverse();
+        }
+
+        levels
+    }
+}
+
+#[cfg(test)]
+mod views_tests {
+    use super::*;
+
+    /// Returns the tree
+    ///
+    ///          1
+    ///        /   \
+    ///       2     3
+    ///      / \     \
+    ///     4   5     6
+    ///          \
+    ///           7
+    ///            \
+    ///             8
+    fn sample_tree() -> Tree {
+        let mut tree = Tree::with_root(1);
+
+        tree.add_node(0, 2, true); // id 1
+        tree.add_node(0, 3, false); // id 2
+        tree.add_node(1, 4, true); // id 3
+        tree.add_node(1, 5, false); // id 4
+        tree.add_node(2, 6, false); // id 5
+        tree.add_node(4, 7, false); // id 6
+        tree.add_node(6, 8, false); // id 7
+
+        tree
+    }
+
+    #[test]
+    fn side_views() {
+        let tree = sample_tree();
+
+        assert_eq!(tree.left_view(), vec![1, 2, 4, 7, 8]);
+        assert_eq!(tree.right_view(), vec![1, 3, 6, 7, 8]);
+    }
+
+    #[test]
+    fn top_and_bottom_views() {
+        let tree = sample_tree();
+
+        // Columns: -2: [4], -1: [2], 0: [1, 5], 1: [3, 7], 2: [6, 8]
+        assert_eq!(tree.top_view(), vec![4, 2, 1, 3, 6]);
+        assert_eq!(tree.bottom_view(), vec![4, 2, 5, 7, 8]);
+    }
+
+    #[test]
+    fn vertical_order() {
+        let tree = sample_tree();
+
+        assert_eq!(
+            tree.vertical_order(),
+            vec![vec![4], vec![2], vec![1, 5], vec![3, 7], vec![6, 8]]
+        );
+    }
+
+    #[test]
+    fn zigzag_level_order() {
+        let tree = sample_tree();
+
+        assert_eq!(
+            tree.zigzag_level_order(),
+            vec![vec![1], vec![3, 2], vec![4, 5, 6], vec![7], vec![8]]
+        );
+    }
+
+    #[test]
+    fn single_node() {
+        let tree = Tree::with_root(10);
+
+        assert_eq!(tree.left_view(), vec![10]);
+        assert_eq!(tree.right_view(), vec![10]);
+        assert_eq!(tree.top_view(), vec![10]);
+        assert_eq!(tree.bottom_view(), vec![10]);
+        assert_eq!(tree.vertical_order(), vec![vec![10]]);
+        assert_eq!(tree.zigzag_level_order(), vec![vec![10]]);
+    }
+
+    #[test]
+    fn bottom_view_ties() {
+        let mut tree = Tree::with_root(1);
+
+        //       1
+        //     /   \
+        //    2     3
+        //     \   /
+        //      4 5
+        tree.add_node(0, 2, true); // id 1
+        tree.add_node(0, 3, false); // id 2
+        tree.add_node(1, 4, false); // id 3
+        tree.add_node(2, 5, true); // id 4
+
+        // 4 and 5 share the column of the root on the same level
+        assert_eq!(tree.top_view(), vec![2, 1, 3]);
+        assert_eq!(tree.bottom_view(), vec![2, 5, 3]);
+        assert_eq!(tree.vertical_order(), vec![vec![2], vec![1, 4, 5], vec![3]]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Compares the recursive `MinMax` and the iterative `IterMinMax` over the inputs
+//! of Testset_handson2_p1, checking that they give the same results.
+//!
+//! Run with `cargo bench --bench backends` from the crate directory.
+
+use hands_on_2::iterative::IterMinMax;
+use hands_on_2::MinMax;
+use std::time::Duration;
+
+mod common;
+use common::time;
+
+const ROUNDS: u32 = 10_000;
+
+/// Operation of the testset, with 0-based inclusive positions
+enum Op {
+    ChMin(usize, usize, i32),
+    Max(usize, usize),
+}
+
+/// Reads the initial values and the operations of a testset input, or returns
+/// what is wrong with it.
+fn read_input(path: &str) -> Result<(Vec<i32>, Vec<Op>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut lines = text.lines().skip(1);
+
+    let values: Vec<i32> = lines
+        .next()
+        .ok_or(format!("{}: missing values", path))?
+        .split_whitespace()
+        .filter_map(|s| s.parse().ok())
+        .collect();
+
+    let mut ops: Vec<Op> = Vec::new();
+    for line in lines {
+        let numbers: Vec<i32> = line.split_whitespace().filter_map(|s| s.parse().ok()).collect();
+
+        // The testset positions are 1-based
+        let zero_based = |position: i32| {
+            usize::try_from(position)
+                .ok()
+                .and_then(|position| position.checked_sub(1))
+                .ok_or(format!("{}: invalid 1-based position {}", path, position))
+        };
+        let (start, end) = (zero_based(numbers[1])?, zero_based(numbers[2])?);
+
+        ops.push(match numbers[0] {
+            0 => Op::ChMin(start, end, numbers[3]),
+            _ => Op::Max(start, end),
+        });
+    }
+
+    Ok((values, ops))
+}
+
+fn run_min_max(values: &[i32], ops: &[Op]) -> Vec<i32> {
+    let mut tree = MinMax::new(values.to_vec());
+    let mut results: Vec<i32> = Vec::new();
+
+    for op in ops {
+        match *op {
+            Op::ChMin(start, end, t) => tree.chmin(start..=end, t).unwrap(),
+            Op::Max(start, end) => results.push(tree.max(start..=end).unwrap()),
+        }
+    }
+
+    results
+}
+
+fn run_iterative(values: &[i32], ops: &[Op]) -> Vec<i32> {
+    let mut tree = IterMinMax::new(values);
+    let mut results: Vec<i32> = Vec::new();
+
+    for op in ops {
+        match *op {
+            Op::ChMin(start, end, t) => tree.chmin(start..=end, t).unwrap(),
+            Op::Max(start, end) => results.push(tree.max(start..=end).unwrap()),
+        }
+    }
+
+    results
+}
+
+fn main() {
+    let mut totals = [Duration::ZERO; 2];
+
+    for i in 0..=10 {
+        let path = format!("./Testset_handson2_p1/input{}.txt", i);
+        let (values, ops) = match read_input(&path) {
+            Ok(input) => input,
+            Err(error) => {
+                println!("input{:<2}  skipped, {}", i, error);
+                continue;
+            }
+        };
+        assert_eq!(run_min_max(&values, &ops), run_iterative(&values, &ops));
+
+        let elapsed = [
+            time(ROUNDS, || run_min_max(&values, &ops)),
+            time(ROUNDS, || run_iterative(&values, &ops)),
+        ];
+        println!(
+            "input{:<2}  n = {:>4}  ops = {:>4}   MinMax {:>9.2} us   IterMinMax {:>9.2} us",
+            i,
+            values.len(),
+            ops.len(),
+            elapsed[0].as_secs_f64() * 1e6,
+            elapsed[1].as_secs_f64() * 1e6
+        );
+
+        totals[0] += elapsed[0];
+        totals[1] += elapsed[1];
+    }
+
+    println!(
+        "total                       MinMax {:>9.2} us   IterMinMax {:>9.2} us   ({:.1}x)",
+        totals[0].as_secs_f64() * 1e6,
+        totals[1].as_secs_f64() * 1e6,
+        totals[0].as_secs_f64() / totals[1].as_secs_f64()
+    );
+}
@@ -0,0 +1,14 @@
+//! Helpers shared by the benchmarks.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Runs `f` `rounds` times and returns the average duration of a run.
+pub fn time<T>(rounds: u32, mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        black_box(f());
+    }
+
+    start.elapsed() / rounds
+}
@@ -0,0 +1,146 @@
+//! Measures the construction time of `MinMax` and `IsThere`, and of `MinMax` as it
+//! was built before, in O(n log n), for comparison.
+//!
+//! Run with `cargo bench --bench construction -- <sizes>...`, 10^6 and 10^7 by default.
+
+use hands_on_2::{IsThere, MinMax};
+
+mod common;
+use common::time;
+
+const ROUNDS: u32 = 5;
+
+/// The construction of `MinMax` before the O(n) build: the nodes of every subtree
+/// are built recursively, then merged level by level, in O(n log n).
+mod baseline {
+    pub struct Range {
+        start: usize,
+        end: usize,
+    }
+
+    pub struct RangeNode {
+        key: i32,
+        range: Range,
+        id_left: Option<usize>,
+        id_right: Option<usize>,
+    }
+
+    impl RangeNode {
+        fn new(key: i32, range: Range) -> Self {
+            Self {
+                key,
+                range,
+                id_left: None,
+                id_right: None,
+            }
+        }
+
+        fn clone_node(&self) -> RangeNode {
+            RangeNode::new(self.key, Range { start: self.range.start, end: self.range.end })
+        }
+    }
+
+    struct LevelNode {
+        range_node: RangeNode,
+        level: usize,
+    }
+
+    impl LevelNode {
+        fn clone_node(&self) -> LevelNode {
+            LevelNode {
+                range_node: self.range_node.clone_node(),
+                level: self.level,
+            }
+        }
+    }
+
+    fn tree_init(arr: &[i32], start: usize, end: usize, level: usize) -> (Vec<LevelNode>, i32) {
+        if start == end {
+            let node = RangeNode::new(arr[start], Range { start, end });
+            return (vec![LevelNode { range_node: node, level }], arr[start]);
+        }
+
+        let mid = (start + end) / 2;
+        let (left_nodes, left_max) = tree_init(arr, start, mid, level + 1);
+        let (right_nodes, right_max) = tree_init(arr, mid + 1, end, level + 1);
+        let maximum = i32::max(left_max, right_max);
+
+        let mut ret_arr: Vec<LevelNode> = Vec::with_capacity(left_nodes.len() + right_nodes.len() + 1);
+        let node = RangeNode::new(maximum, Range { start, end });
+        ret_arr.push(LevelNode { range_node: node, level });
+        merge(&left_nodes, &right_nodes, &mut ret_arr);
+
+        (ret_arr, maximum)
+    }
+
+    fn merge(left: &[LevelNode], right: &[LevelNode], merged_arr: &mut Vec<LevelNode>) {
+        let (mut i, mut j) = (0, 0);
+
+        while i < left.len() && j < right.len() {
+            if left[i].level <= right[j].level {
+                merged_arr.push(left[i].clone_node());
+                i += 1;
+            } else {
+                merged_arr.push(right[j].clone_node());
+                j += 1;
+            }
+        }
+
+        merged_arr.extend(left[i..].iter().map(LevelNode::clone_node));
+        merged_arr.extend(right[j..].iter().map(LevelNode::clone_node));
+    }
+
+    /// Returns the nodes and the lazy tags of the old `MinMax::new(arr)`.
+    pub fn build(arr: &[i32]) -> (Vec<RangeNode>, Vec<Option<i32>>) {
+        let (ranges, _) = tree_init(arr, 0, arr.len() - 1, 0);
+        let mut nodes: Vec<RangeNode> = Vec::with_capacity(ranges.len());
+        let mut leaves = 0;
+
+        for (i, node) in ranges.iter().enumerate() {
+            let mut new_node = node.range_node.clone_node();
+
+            if new_node.range.start != new_node.range.end {
+                new_node.id_left = Some((i - leaves) * 2 + 1);
+                new_node.id_right = Some((i - leaves) * 2 + 2);
+            } else {
+                leaves += 1;
+            }
+            nodes.push(new_node);
+        }
+
+        let lazy = (0..nodes.len()).map(|_| None).collect();
+        (nodes, lazy)
+    }
+}
+
+fn main() {
+    let mut sizes: Vec<usize> = std::env::args()
+        .skip(1)
+        .filter_map(|arg| arg.parse().ok())
+        .collect();
+    if sizes.is_empty() {
+        sizes = vec![1_000_000, 10_000_000];
+    }
+
+    for n in sizes {
+        // Arbitrary values, generated without any dependency
+        let values: Vec<i32> = (0..n as i64)
+            .map(|i| ((i * 7_919 + 13) % 1_000_003) as i32)
+            .collect();
+
+        for (name, elapsed) in [
+            // The O(n log n) build is slow enough for one round
+            ("baseline", time(1, || baseline::build(&values))),
+            ("MinMax", time(ROUNDS, || MinMax::new(values.clone()))),
+            ("IsThere", time(ROUNDS, || IsThere::new(n as u128))),
+        ] {
+            println!(
+                "new()   {:<8} n = {:>10}  {:>10.3} ms  {:>8.1} ns/value",
+                name,
+                n,
+                elapsed.as_secs_f64() * 1e3,
+                elapsed.as_secs_f64() * 1e9 / n as f64
+            );
+        }
+    }
+}
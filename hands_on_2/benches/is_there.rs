@@ -0,0 +1,185 @@
+//! Compares `IsThere` with a plain `LazySegTree` over coverage bitmasks, the one
+//! `IsThere` uses until some coverage reaches 127, over the inputs of
+//! Testset_handson2_p2, checking that they give the same results. `IsThere` also
+//! records the segments added, to remove them.
+//!
+//! A larger input with 2^16 positions follows the testset, which has 10 positions only.
+//!
+//! Run with `cargo bench --bench is_there` from the crate directory.
+
+use hands_on_2::lazy::{Action, LazySegTree, Monoid};
+use hands_on_2::IsThere;
+use std::time::Duration;
+
+mod common;
+use common::time;
+
+const ROUNDS: u32 = 10_000;
+
+/// Operation of the testset, with 0-based inclusive positions
+enum Op {
+    Add(usize, usize),
+    IsThere(usize, usize, usize),
+}
+
+/// Bitwise or of `u128` values.
+struct Or;
+
+impl Monoid for Or {
+    type Value = u128;
+
+    fn identity() -> u128 {
+        0
+    }
+
+    fn combine(left: &u128, right: &u128) -> u128 {
+        left | right
+    }
+}
+
+/// Shifts every value left by the number of bits in the tag.
+struct Shift;
+
+impl Action<Or> for Shift {
+    type Tag = u32;
+
+    fn apply(bits: &u32, value: &u128) -> u128 {
+        value << bits
+    }
+
+    fn compose(outer: &u32, inner: &u32) -> u32 {
+        outer + inner
+    }
+}
+
+/// Reads the number of positions and the operations of a testset input.
+fn read_input(path: &str) -> Result<(usize, Vec<Op>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut lines = text.lines();
+
+    let n: usize = lines
+        .next()
+        .and_then(|line| line.split_whitespace().next())
+        .and_then(|n| n.parse().ok())
+        .ok_or(format!("{}: missing number of positions", path))?;
+
+    let ops: Vec<Op> = lines
+        .map(|line| line.split_whitespace().filter_map(|s| s.parse().ok()).collect())
+        .map(|numbers: Vec<usize>| match numbers[..] {
+            [i, j] => Ok(Op::Add(i, j)),
+            [i, j, k] => Ok(Op::IsThere(i, j, k)),
+            _ => Err(format!("{}: invalid operation {:?}", path, numbers)),
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((n, ops))
+}
+
+/// `m` operations over `n` positions, an add every `m / 120` of them so that no
+/// coverage reaches 128, with arbitrary ranges generated without any dependency.
+fn synthetic_input(n: usize, m: usize) -> Vec<Op> {
+    let mut state: u64 = 2024;
+    let mut next = |below: usize| {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) as usize % below
+    };
+
+    (0..m)
+        .map(|op| {
+            let i = next(n);
+            let j = i + next(n - i);
+
+            if op % (m / 120) == 0 {
+                Op::Add(i, j)
+            } else {
+                Op::IsThere(i, j, next(8))
+            }
+        })
+        .collect()
+}
+
+fn run_is_there(n: usize, ops: &[Op]) -> Vec<bool> {
+    let mut is_there = IsThere::new(n as u128);
+    let mut results: Vec<bool> = Vec::new();
+
+    for op in ops {
+        match *op {
+            Op::Add(i, j) => is_there.add_segment(i, j),
+            Op::IsThere(i, j, k) => results.push(is_there.is_there(i, j, k)),
+        }
+    }
+
+    results
+}
+
+/// The value of a position has the bit `c` set when `c` segments cover it.
+fn run_bitmask(n: usize, ops: &[Op]) -> Vec<bool> {
+    let mut tree: LazySegTree<Or, Shift> = LazySegTree::new(&vec![1; n]);
+    let mut results: Vec<bool> = Vec::new();
+
+    for op in ops {
+        match *op {
+            Op::Add(i, j) => tree.range_apply(i, j, 1),
+            Op::IsThere(i, j, k) => results.push(tree.range_query(i, j) >> k & 1 == 1),
+        }
+    }
+
+    results
+}
+
+fn main() {
+    let mut totals = [Duration::ZERO; 2];
+
+    let mut inputs: Vec<(String, usize, Vec<Op>)> = Vec::new();
+    for i in 0..=7 {
+        match read_input(&format!("./Testset_handson2_p2/input{}.txt", i)) {
+            Ok((n, ops)) => inputs.push((format!("input{}", i), n, ops)),
+            Err(error) => println!("input{}  skipped, {}", i, error),
+        }
+    }
+
+    for (name, n, ops) in &inputs {
+        let (n, ops) = (*n, ops.as_slice());
+        assert_eq!(run_is_there(n, ops), run_bitmask(n, ops));
+
+        let elapsed = [
+            time(ROUNDS, || run_is_there(n, ops)),
+            time(ROUNDS, || run_bitmask(n, ops)),
+        ];
+        println!(
+            "{:<6}  n = {:>4}  ops = {:>4}   IsThere {:>9.2} us   bitmask {:>9.2} us",
+            name,
+            n,
+            ops.len(),
+            elapsed[0].as_secs_f64() * 1e6,
+            elapsed[1].as_secs_f64() * 1e6
+        );
+
+        totals[0] += elapsed[0];
+        totals[1] += elapsed[1];
+    }
+
+    println!(
+        "total                       IsThere {:>9.2} us   bitmask {:>9.2} us   ({:.1}x)",
+        totals[0].as_secs_f64() * 1e6,
+        totals[1].as_secs_f64() * 1e6,
+        totals[1].as_secs_f64() / totals[0].as_secs_f64()
+    );
+
+    let n = 1 << 16;
+    let ops = synthetic_input(n, 20_000);
+    assert_eq!(run_is_there(n, &ops), run_bitmask(n, &ops));
+
+    let elapsed = [
+        time(10, || run_is_there(n, &ops)),
+        time(10, || run_bitmask(n, &ops)),
+    ];
+    println!(
+        "large   n = {}  ops = {}   IsThere {:>9.2} ms   bitmask {:>9.2} ms   ({:.1}x)",
+        n,
+        ops.len(),
+        elapsed[0].as_secs_f64() * 1e3,
+        elapsed[1].as_secs_f64() * 1e3,
+        elapsed[1].as_secs_f64() / elapsed[0].as_secs_f64()
+    );
+}
@@ -0,0 +1,323 @@
+use crate::lazy::{build_nodes, children, middle};
+
+/// Summary of a range: its maximum with the strict second maximum and the number
+/// of positions holding the maximum, the same for the minimum, and the sum.
+/// A missing second maximum (minimum) is `i64::MIN` (`i64::MAX`), which no
+/// `i32` value can be.
+#[derive(Clone, Copy)]
+struct Beats {
+    max: i64,
+    second_max: i64,
+    max_count: usize,
+    min: i64,
+    second_min: i64,
+    min_count: usize,
+    sum: i64,
+}
+
+impl Beats {
+    fn leaf(value: i32) -> Self {
+        Self {
+            max: value as i64,
+            second_max: i64::MIN,
+            max_count: 1,
+            min: value as i64,
+            second_min: i64::MAX,
+            min_count: 1,
+            sum: value as i64,
+        }
+    }
+
+    fn combine(left: &Beats, right: &Beats) -> Beats {
+        let (max, second_max, max_count) = if left.max == right.max {
+            (left.max, left.second_max.max(right.second_max), left.max_count + right.max_count)
+        } else if left.max > right.max {
+            (left.max, left.second_max.max(right.max), left.max_count)
+        } else {
+            (right.max, left.max.max(right.second_max), right.max_count)
+        };
+
+        let (min, second_min, min_count) = if left.min == right.min {
+            (left.min, left.second_min.min(right.second_min), left.min_count + right.min_count)
+        } else if left.min < right.min {
+            (left.min, left.second_min.min(right.min), left.min_count)
+        } else {
+            (right.min, left.min.min(right.second_min), right.min_count)
+        };
+
+        Beats {
+            max,
+            second_max,
+            max_count,
+            min,
+            second_min,
+            min_count,
+            sum: left.sum + right.sum,
+        }
+    }
+
+    /// Lowers the maximum to `t`, which must be above the second maximum.
+    fn lower_max(&mut self, t: i64) {
+        self.sum -= (self.max - t) * self.max_count as i64;
+
+        // The maximum may also be the minimum or the second minimum
+        if self.min == self.max {
+            self.min = t;
+        } else if self.second_min == self.max {
+            self.second_min = t;
+        }
+        self.max = t;
+    }
+
+    /// Raises the minimum to `t`, which must be below the second minimum.
+    fn raise_min(&mut self, t: i64) {
+        self.sum += (t - self.min) * self.min_count as i64;
+
+        if self.max == self.min {
+            self.max = t;
+        } else if self.second_max == self.min {
+            self.second_max = t;
+        }
+        self.min = t;
+    }
+}
+
+/// Segment Tree Beats (Ji's segment tree): range chmin and range chmax updates
+/// with range sum, maximum and minimum queries.
+///
+/// An update stops at the nodes where it changes only the maximum (minimum), so it
+/// can fix the sum from the number of positions holding it. The other nodes are
+/// visited further, which costs O(log² n) amortized per operation.
+/// There are no explicit lazy tags: the maximum and minimum of a node bound the
+/// ones of its children, and are pushed down before visiting them.
+/// Positions are 0-based and ranges are inclusive.
+pub struct MinMaxBeats {
+    /// Summary of every node, in the layout of `build_nodes`
+    nodes: Vec<Beats>,
+    len: usize,
+}
+
+impl MinMaxBeats {
+    /// Builds the tree over `arr` in O(n).
+    ///
+    /// # Panics
+    /// Panics if `arr` is empty.
+    pub fn new(arr: &[i32]) -> Self {
+        let leaves: Vec<Beats> = arr.iter().map(|&value| Beats::leaf(value)).collect();
+
+        Self {
+            nodes: build_nodes(&leaves, Beats::combine),
+            len: arr.len(),
+        }
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`, since the tree can't be built over no values.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Sets every `a[i]` in `[start, end]` to `min(a[i], t)`.
+    pub fn chmin(&mut self, start: usize, end: usize, t: i32) {
+        self.chmin_node(start, end, t as i64, 0, 0, self.len - 1);
+    }
+
+    /// Sets every `a[i]` in `[start, end]` to `max(a[i], t)`.
+    pub fn chmax(&mut self, start: usize, end: usize, t: i32) {
+        self.chmax_node(start, end, t as i64, 0, 0, self.len - 1);
+    }
+
+    /// Returns the sum of the values in `[start, end]`, 0 if the range is empty
+    /// or out of bounds.
+    pub fn range_sum(&mut self, start: usize, end: usize) -> i64 {
+        self.query(start, end, 0, 0, self.len - 1).map_or(0, |beats| beats.sum)
+    }
+
+    /// Returns the maximum of the values in `[start, end]`, `None` if the range
+    /// is empty or out of bounds.
+    pub fn range_max(&mut self, start: usize, end: usize) -> Option<i32> {
+        self.query(start, end, 0, 0, self.len - 1).map(|beats| beats.max as i32)
+    }
+
+    /// Returns the minimum of the values in `[start, end]`, `None` if the range
+    /// is empty or out of bounds.
+    pub fn range_min(&mut self, start: usize, end: usize) -> Option<i32> {
+        self.query(start, end, 0, 0, self.len - 1).map(|beats| beats.min as i32)
+    }
+
+    /// Returns the maximum of the values in `[start, end]` and the number of
+    /// positions holding it, `None` if the range is empty or out of bounds.
+    pub fn range_max_count(&mut self, start: usize, end: usize) -> Option<(i32, usize)> {
+        self.query(start, end, 0, 0, self.len - 1)
+            .map(|beats| (beats.max as i32, beats.max_count))
+    }
+
+    /// `node` covers `[lo, hi]`.
+    fn query(&mut self, start: usize, end: usize, node: usize, lo: usize, hi: usize) -> Option<Beats> {
+        // No overlap
+        if lo > end || hi < start {
+            return None;
+        }
+
+        // Full overlap
+        if lo >= start && hi <= end {
+            return Some(self.nodes[node]);
+        }
+
+        // Partial overlap
+        self.push(node);
+        let (left_id, right_id) = children(node);
+        let mid = middle(lo, hi);
+        let left = self.query(start, end, left_id, lo, mid);
+        let right = self.query(start, end, right_id, mid + 1, hi);
+
+        match (left, right) {
+            (Some(left), Some(right)) => Some(Beats::combine(&left, &right)),
+            (left, right) => left.or(right),
+        }
+    }
+
+    /// `node` covers `[lo, hi]`.
+    fn chmin_node(&mut self, start: usize, end: usize, t: i64, node: usize, lo: usize, hi: usize) {
+        // No overlap, or nothing above t
+        if lo > end || hi < start || self.nodes[node].max <= t {
+            return;
+        }
+
+        // Full overlap, and only the maximum changes
+        if lo >= start && hi <= end && self.nodes[node].second_max < t {
+            self.nodes[node].lower_max(t);
+            return;
+        }
+
+        self.push(node);
+        let (left_id, right_id) = children(node);
+        let mid = middle(lo, hi);
+
+        self.chmin_node(start, end, t, left_id, lo, mid);
+        self.chmin_node(start, end, t, right_id, mid + 1, hi);
+
+        self.pull(node);
+    }
+
+    /// `node` covers `[lo, hi]`.
+    fn chmax_node(&mut self, start: usize, end: usize, t: i64, node: usize, lo: usize, hi: usize) {
+        // No overlap, or nothing below t
+        if lo > end || hi < start || self.nodes[node].min >= t {
+            return;
+        }
+
+        // Full overlap, and only the minimum changes
+        if lo >= start && hi <= end && self.nodes[node].second_min > t {
+            self.nodes[node].raise_min(t);
+            return;
+        }
+
+        self.push(node);
+        let (left_id, right_id) = children(node);
+        let mid = middle(lo, hi);
+
+        self.chmax_node(start, end, t, left_id, lo, mid);
+        self.chmax_node(start, end, t, right_id, mid + 1, hi);
+
+        self.pull(node);
+    }
+
+    /// Brings the children of `node`, which is not a leaf, within its maximum and minimum.
+    fn push(&mut self, node: usize) {
+        let Beats { max, min, .. } = self.nodes[node];
+        let (left_id, right_id) = children(node);
+
+        for child in [left_id, right_id] {
+            let key = &mut self.nodes[child];
+
+            if key.max > max {
+                key.lower_max(max);
+            }
+            if key.min < min {
+                key.raise_min(min);
+            }
+        }
+    }
+
+    fn pull(&mut self, node: usize) {
+        let (left_id, right_id) = children(node);
+
+        self.nodes[node] = Beats::combine(&self.nodes[left_id], &self.nodes[right_id]);
+    }
+}
+
+#[cfg(test)]
+mod beats_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn random_against_naive() {
+        let mut rng = Rng::new(41);
+
+        for _ in 0..500 {
+            let n = 1 + rng.below(40);
+            let mut naive: Vec<i32> = (0..n).map(|_| rng.key(-50..=50)).collect();
+            let mut beats = MinMaxBeats::new(&naive);
+            assert_eq!(beats.len(), n);
+
+            for _ in 0..100 {
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+                let slice = &mut naive[start..=end];
+
+                match rng.below(4) {
+                    0 => {
+                        let t = rng.key(-50..=50);
+                        beats.chmin(start, end, t);
+                        slice.iter_mut().for_each(|x| *x = (*x).min(t));
+                    }
+                    1 => {
+                        let t = rng.key(-50..=50);
+                        beats.chmax(start, end, t);
+                        slice.iter_mut().for_each(|x| *x = (*x).max(t));
+                    }
+                    _ => {
+                        let max = slice.iter().max().copied();
+                        let count = slice.iter().filter(|&&x| Some(x) == max).count();
+
+                        assert_eq!(beats.range_sum(start, end), slice.iter().map(|&x| x as i64).sum());
+                        assert_eq!(beats.range_max(start, end), max);
+                        assert_eq!(beats.range_min(start, end), slice.iter().min().copied());
+                        assert_eq!(beats.range_max_count(start, end), max.map(|max| (max, count)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_values() {
+        let mut beats = MinMaxBeats::new(&[i32::MIN, i32::MAX, 0, i32::MAX]);
+
+        assert_eq!(beats.range_sum(0, 3), i32::MAX as i64 - 1);
+        assert_eq!(beats.range_max_count(0, 3), Some((i32::MAX, 2)));
+
+        beats.chmin(0, 3, i32::MIN);
+        assert_eq!(beats.range_max(0, 3), Some(i32::MIN));
+        assert_eq!(beats.range_sum(0, 3), 4 * i32::MIN as i64);
+
+        beats.chmax(1, 2, i32::MAX);
+        assert_eq!(beats.range_min(1, 2), Some(i32::MAX));
+        assert_eq!(beats.range_max_count(0, 3), Some((i32::MAX, 2)));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut beats = MinMaxBeats::new(&[3, 1, 2]);
+
+        assert_eq!(beats.range_max(3, 10), None);
+        assert_eq!(beats.range_sum(3, 10), 0);
+        assert_eq!(beats.range_min(1, 10), Some(1));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::IsThere;
+
+/// `IsThere` over coordinates as large as 10^18 and more, built offline.
+///
+/// All the segments and query ranges must be given in advance: their endpoints cut
+/// the coordinates into cells whose points are always covered by the same segments.
+/// An `IsThere` over the cells, weighted by their number of points, gives the same
+/// answers of an `IsThere` over all the points, with O(log r) updates for r ranges.
+/// Coordinates are inclusive and must be below `u64::MAX`.
+pub struct CompressedIsThere {
+    /// First and last coordinate of every cell, in increasing order
+    cells: Vec<(u64, u64)>,
+    is_there: IsThere,
+}
+
+impl CompressedIsThere {
+    /// Builds the structure for the segments and query ranges in `ranges`,
+    /// none of them covered yet.
+    ///
+    /// # Panics
+    /// Panics if `ranges` is empty or contains an empty range, or a range that
+    /// ends at `u64::MAX`.
+    pub fn new(ranges: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        let mut cuts: Vec<u64> = Vec::new();
+
+        for (i, j) in ranges {
+            assert!(i <= j, "The range is empty");
+            assert!(j < u64::MAX, "The range ends at u64::MAX");
+
+            // Every range starts a cell, and the next one starts right after it
+            cuts.push(i);
+            cuts.push(j + 1);
+        }
+        assert!(!cuts.is_empty(), "There must be at least one range");
+
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        let cells: Vec<(u64, u64)> = cuts.windows(2).map(|cut| (cut[0], cut[1] - 1)).collect();
+        let weights: Vec<u64> = cells.iter().map(|&(start, end)| end - start + 1).collect();
+
+        Self {
+            is_there: IsThere::build(cells.len(), Some(weights)),
+            cells,
+        }
+    }
+
+    /// Returns the number of cells, which is less than twice the number of ranges.
+    pub fn cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Adds the segment `[i, j]`.
+    ///
+    /// # Panics
+    /// Panics if the range was not given to `new`.
+    pub fn add_segment(&mut self, i: u64, j: u64) {
+        let (start, end) = self.to_cells(i, j);
+        self.is_there.add_segment(start, end);
+    }
+
+    /// Removes a segment `[i, j]` added before. Returns `false`, changing nothing,
+    /// if there is no such segment.
+    ///
+    /// # Panics
+    /// Panics if the range was not given to `new`.
+    pub fn remove_segment(&mut self, i: u64, j: u64) -> bool {
+        let (start, end) = self.to_cells(i, j);
+        self.is_there.remove_segment(start, end)
+    }
+
+    /// Returns `true` iff some point in `[i, j]` is covered by exactly `k` segments.
+    ///
+    /// # Panics
+    /// Panics if the range was not given to `new`.
+    pub fn is_there(&mut self, i: u64, j: u64, k: usize) -> bool {
+        let (start, end) = self.to_cells(i, j);
+        self.is_there.is_there(start, end, k)
+    }
+
+    /// Returns the number of points in `[i, j]` covered by exactly `k` segments.
+    ///
+    /// # Panics
+    /// Panics if the range was not given to `new`.
+    pub fn count_exact(&mut self, i: u64, j: u64, k: usize) -> u64 {
+        let (start, end) = self.to_cells(i, j);
+        self.is_there.weight_exact(start, end, k)
+    }
+
+    /// Returns the points in `[i, j]` covered by exactly `k` segments, as disjoint
+    /// ranges in increasing order, with no two of them adjacent.
+    ///
+    /// # Panics
+    /// Panics if the range was not given to `new`.
+    pub fn positions_exact(&mut self, i: u64, j: u64, k: usize) -> Vec<(u64, u64)> {
+        let (start, end) = self.to_cells(i, j);
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+
+        for cell in self.is_there.positions_exact(start, end, k) {
+            let (start, end) = self.cells[cell];
+
+            match ranges.last_mut() {
+                Some(last) if last.1 + 1 == start => last.1 = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        ranges
+    }
+
+    /// Returns the first and the last cell of `[i, j]`.
+    fn to_cells(&self, i: u64, j: u64) -> (usize, usize) {
+        let start = self.cells.binary_search_by_key(&i, |&(start, _)| start);
+        let end = self.cells.binary_search_by_key(&j, |&(_, end)| end);
+
+        match (start, end) {
+            (Ok(start), Ok(end)) if start <= end => (start, end),
+            _ => panic!("The range [{}, {}] was not given to new", i, j),
+        }
+    }
+}
+
+#[cfg(test)]
+mod compressed_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn same_answers_as_dense() {
+        let mut rng = Rng::new(45);
+        let base: u64 = 1_000_000_000_000_000_000;
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(60);
+            let ops: Vec<(usize, usize, usize, usize)> = (0..100)
+                .map(|_| {
+                    let i = rng.below(n);
+                    (rng.below(4), i, i + rng.below(n - i), rng.below(5))
+                })
+                .collect();
+
+            let ranges = ops.iter().map(|&(_, i, j, _)| (base + i as u64, base + j as u64));
+            let mut compressed = CompressedIsThere::new(ranges);
+            let mut dense = IsThere::new(n as u128);
+            assert!(compressed.cells() < 2 * ops.len());
+
+            for (op, i, j, k) in ops {
+                let (x, y) = (base + i as u64, base + j as u64);
+
+                match op {
+                    0 => {
+                        dense.add_segment(i, j);
+                        compressed.add_segment(x, y);
+                    }
+                    1 => assert_eq!(compressed.remove_segment(x, y), dense.remove_segment(i, j)),
+                    _ => {
+                        assert_eq!(compressed.is_there(x, y, k), dense.is_there(i, j, k));
+                        assert_eq!(compressed.count_exact(x, y, k), dense.count_exact(i, j, k) as u64);
+
+                        let points: Vec<usize> = compressed
+                            .positions_exact(x, y, k)
+                            .into_iter()
+                            .flat_map(|(start, end)| start - base..=end - base)
+                            .map(|point| point as usize)
+                            .collect();
+                        assert_eq!(points, dense.positions_exact(i, j, k));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        let segments = [(0, 999_999_999), (500_000_000, 1_499_999_999), (10u64.pow(18), u64::MAX - 1)];
+        let query = (0, u64::MAX - 1);
+
+        let mut compressed = CompressedIsThere::new(segments.into_iter().chain([query]));
+        for (i, j) in segments {
+            compressed.add_segment(i, j);
+        }
+
+        assert!(compressed.is_there(query.0, query.1, 2));
+        assert!(!compressed.is_there(query.0, query.1, 3));
+        assert_eq!(compressed.count_exact(query.0, query.1, 2), 500_000_000);
+        assert_eq!(
+            compressed.count_exact(query.0, query.1, 0),
+            10u64.pow(18) - 1_500_000_000
+        );
+        assert_eq!(
+            compressed.positions_exact(query.0, query.1, 1),
+            vec![(0, 499_999_999), (1_000_000_000, 1_499_999_999), (10u64.pow(18), u64::MAX - 1)]
+        );
+
+        assert!(compressed.remove_segment(500_000_000, 1_499_999_999));
+        assert_eq!(compressed.positions_exact(0, 999_999_999, 1), vec![(0, 999_999_999)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "was not given to new")]
+    fn unknown_range() {
+        let mut compressed = CompressedIsThere::new([(10, 20)]);
+        compressed.is_there(10, 15, 0);
+    }
+}
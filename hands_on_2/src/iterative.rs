@@ -0,0 +1,197 @@
+use crate::{inclusive_bounds, RangeError};
+use std::ops::RangeBounds;
+
+/// `MinMax` on a non-recursive segment tree, usually faster than the recursive one.
+///
+/// The number of leaves is rounded up to a power of two, so the tree is implicit:
+/// node `k` has children `2k` and `2k + 1`, and leaf `i` is node `size + i`.
+/// Operations walk the two boundaries of the range bottom-up, pushing the pending
+/// tags down along them first and recomputing the maximums after an update.
+/// Both `chmin` and `max` take O(log n), with the same results of `MinMax`.
+/// Positions are 0-based.
+pub struct IterMinMax {
+    len: usize,
+    /// Number of leaves, a power of two
+    size: usize,
+    /// Height of the tree, `size == 1 << log`
+    log: u32,
+    /// Maximum of every node, `i32::MIN` for the leaves past `len`
+    max: Vec<i32>,
+    /// Chmin to push down to the children of every internal node, `i32::MAX` if none
+    lazy: Vec<i32>,
+}
+
+impl IterMinMax {
+    /// Builds the tree over `arr` in O(n).
+    ///
+    /// # Panics
+    /// Panics if `arr` is empty.
+    pub fn new(arr: &[i32]) -> Self {
+        assert!(!arr.is_empty(), "The segment tree needs at least one value");
+
+        let size = arr.len().next_power_of_two();
+        let mut max = vec![i32::MIN; 2 * size];
+        max[size..size + arr.len()].copy_from_slice(arr);
+
+        let mut tree = Self {
+            len: arr.len(),
+            size,
+            log: size.trailing_zeros(),
+            max,
+            lazy: vec![i32::MAX; size],
+        };
+        for node in (1..size).rev() {
+            tree.pull(node);
+        }
+
+        tree
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`, since the tree can't be built over no values.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Sets every value `x` in `range` to `min(x, t)`. O(log n).
+    pub fn chmin(&mut self, range: impl RangeBounds<usize>, t: i32) -> Result<(), RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len)?;
+        let (left, right) = (start + self.size, end + 1 + self.size);
+        self.push_boundaries(left, right);
+
+        let (mut l, mut r) = (left, right);
+        while l < r {
+            if l & 1 == 1 {
+                self.apply(l, t);
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                self.apply(r, t);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+
+        // Only the ancestors of the boundaries may be partially covered
+        for i in 1..=self.log {
+            if (left >> i) << i != left {
+                self.pull(left >> i);
+            }
+            if (right >> i) << i != right {
+                self.pull((right - 1) >> i);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the maximum of the values in `range`. O(log n).
+    pub fn max(&mut self, range: impl RangeBounds<usize>) -> Result<i32, RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len)?;
+        let (mut l, mut r) = (start + self.size, end + 1 + self.size);
+        self.push_boundaries(l, r);
+
+        let mut max = i32::MIN;
+        while l < r {
+            if l & 1 == 1 {
+                max = max.max(self.max[l]);
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                max = max.max(self.max[r]);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+
+        Ok(max)
+    }
+
+    /// Pushes the tags down along the paths from the root to the leaves `left`
+    /// and `right - 1`, skipping the nodes fully inside `[left, right)`.
+    fn push_boundaries(&mut self, left: usize, right: usize) {
+        for i in (1..=self.log).rev() {
+            if (left >> i) << i != left {
+                self.push(left >> i);
+            }
+            if (right >> i) << i != right {
+                self.push((right - 1) >> i);
+            }
+        }
+    }
+
+    fn apply(&mut self, node: usize, t: i32) {
+        self.max[node] = self.max[node].min(t);
+        if node < self.size {
+            self.lazy[node] = self.lazy[node].min(t);
+        }
+    }
+
+    fn push(&mut self, node: usize) {
+        if self.lazy[node] != i32::MAX {
+            self.apply(2 * node, self.lazy[node]);
+            self.apply(2 * node + 1, self.lazy[node]);
+            self.lazy[node] = i32::MAX;
+        }
+    }
+
+    fn pull(&mut self, node: usize) {
+        self.max[node] = self.max[2 * node].max(self.max[2 * node + 1]);
+    }
+}
+
+#[cfg(test)]
+mod iterative_tests {
+    use super::*;
+    use crate::random::Rng;
+    use crate::MinMax;
+
+    #[test]
+    fn same_results_as_min_max() {
+        let mut rng = Rng::new(48);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(70);
+            let arr: Vec<i32> = (0..n).map(|_| rng.key(-50..=50)).collect();
+            let mut naive = arr.clone();
+            let mut min_max = MinMax::new(arr.clone());
+            let mut iterative = IterMinMax::new(&arr);
+            assert_eq!(iterative.len(), n);
+
+            for _ in 0..100 {
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+
+                if rng.coin() {
+                    let t = rng.key(-50..=50);
+                    iterative.chmin(start..=end, t).unwrap();
+                    min_max.chmin(start..=end, t).unwrap();
+                    naive[start..=end].iter_mut().for_each(|x| *x = (*x).min(t));
+                } else {
+                    let expected = *naive[start..=end].iter().max().unwrap();
+                    assert_eq!(iterative.max(start..=end), Ok(expected));
+                    assert_eq!(min_max.max(start..=end), Ok(expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_values() {
+        let mut iterative = IterMinMax::new(&[i32::MIN, i32::MAX, 0]);
+
+        assert_eq!(iterative.max(..), Ok(i32::MAX));
+        iterative.chmin(.., i32::MAX).unwrap();
+        assert_eq!(iterative.max(1..2), Ok(i32::MAX));
+        iterative.chmin(1..=1, i32::MIN).unwrap();
+        assert_eq!(iterative.max(..), Ok(0));
+        assert_eq!(iterative.max(0..2), Ok(i32::MIN));
+        assert_eq!(iterative.max(3..), Err(RangeError::Empty));
+    }
+}
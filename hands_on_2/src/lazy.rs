@@ -0,0 +1,358 @@
+/// A set of values with an associative `combine` and its `identity`,
+/// which is what a segment tree stores in every node.
+pub trait Monoid {
+    type Value: Copy;
+
+    fn identity() -> Self::Value;
+
+    fn combine(left: &Self::Value, right: &Self::Value) -> Self::Value;
+}
+
+/// An update that can be applied lazily to the values of a `Monoid`.
+///
+/// `apply` must distribute over `combine`, so that a tag can be applied to the
+/// combined value of a range without visiting its elements, and `compose` must
+/// give the tag equivalent to applying `inner` first and then `outer`.
+pub trait Action<M: Monoid> {
+    type Tag: Copy;
+
+    fn apply(tag: &Self::Tag, value: &M::Value) -> M::Value;
+
+    fn compose(outer: &Self::Tag, inner: &Self::Tag) -> Self::Tag;
+}
+
+/// Segment tree over the values of `M` with range updates of `A`, applied lazily.
+///
+/// Every node stores the combined value of its range with all the tags above it
+/// already applied, and the tag still to be pushed down to its children.
+/// The nodes are a flat array, see `build_nodes`, and their ranges are computed
+/// while walking down from the root.
+/// Positions are 0-based and ranges are inclusive.
+pub struct LazySegTree<M: Monoid, A: Action<M>> {
+    nodes: Vec<M::Value>,
+    lazy_nodes: Vec<Option<A::Tag>>,
+    len: usize,
+}
+
+impl<M: Monoid, A: Action<M>> LazySegTree<M, A> {
+    /// Builds the tree over `values` in O(n), see `build_nodes`.
+    ///
+    /// # Panics
+    /// Panics if `values` is empty.
+    pub fn new(values: &[M::Value]) -> Self {
+        let nodes = build_nodes(values, M::combine);
+        let lazy_nodes = vec![None; nodes.len()];
+
+        Self {
+            nodes,
+            lazy_nodes,
+            len: values.len(),
+        }
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`, since the tree can't be built over no values.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns the combination of the values in `[start, end]`, or the identity
+    /// if the range is empty or out of bounds. O(log n).
+    pub fn range_query(&mut self, start: usize, end: usize) -> M::Value {
+        self.query_node(start, end, 0, 0, self.len - 1)
+    }
+
+    /// Applies `tag` to every value in `[start, end]`. O(log n).
+    pub fn range_apply(&mut self, start: usize, end: usize, tag: A::Tag) {
+        self.apply_range(start, end, &tag, 0, 0, self.len - 1);
+    }
+
+    /// Returns the first position `i >= start` such that `pred` holds for the value
+    /// at `i`, or `None` if there is none. O(log n).
+    ///
+    /// The search walks down the tree skipping the nodes where `pred` doesn't hold,
+    /// so `pred` must hold for the combined value of a range iff it holds for some
+    /// value in it, like `|max| max >= x` for the maximum. If it holds for more
+    /// ranges, the result is the same, but the search visits more nodes.
+    pub fn find_first(&mut self, start: usize, pred: impl Fn(&M::Value) -> bool) -> Option<usize> {
+        self.find_first_node(start, &pred, 0, 0, self.len - 1)
+    }
+
+    /// Returns the last position `i <= end` such that `pred` holds for the value
+    /// at `i`, or `None` if there is none. O(log n). `pred` must be as in `find_first`.
+    pub fn find_last(&mut self, end: usize, pred: impl Fn(&M::Value) -> bool) -> Option<usize> {
+        self.find_last_node(end, &pred, 0, 0, self.len - 1)
+    }
+
+    /// `node` covers `[lo, hi]`.
+    fn find_first_node(
+        &mut self,
+        start: usize,
+        pred: &impl Fn(&M::Value) -> bool,
+        node: usize,
+        lo: usize,
+        hi: usize,
+    ) -> Option<usize> {
+        if hi < start || !pred(&self.nodes[node]) {
+            return None;
+        }
+        if lo == hi {
+            return Some(lo);
+        }
+
+        // A node partially before start may hold pred only there, then the right child is next
+        self.push(node);
+        let (left_id, right_id) = children(node);
+        let mid = middle(lo, hi);
+
+        self.find_first_node(start, pred, left_id, lo, mid)
+            .or_else(|| self.find_first_node(start, pred, right_id, mid + 1, hi))
+    }
+
+    /// `node` covers `[lo, hi]`.
+    fn find_last_node(
+        &mut self,
+        end: usize,
+        pred: &impl Fn(&M::Value) -> bool,
+        node: usize,
+        lo: usize,
+        hi: usize,
+    ) -> Option<usize> {
+        if lo > end || !pred(&self.nodes[node]) {
+            return None;
+        }
+        if lo == hi {
+            return Some(lo);
+        }
+
+        self.push(node);
+        let (left_id, right_id) = children(node);
+        let mid = middle(lo, hi);
+
+        self.find_last_node(end, pred, right_id, mid + 1, hi)
+            .or_else(|| self.find_last_node(end, pred, left_id, lo, mid))
+    }
+
+    /// `node` covers `[lo, hi]`.
+    fn query_node(&mut self, start: usize, end: usize, node: usize, lo: usize, hi: usize) -> M::Value {
+        // No overlap
+        if lo > end || hi < start {
+            return M::identity();
+        }
+
+        // Full overlap
+        if lo >= start && hi <= end {
+            return self.nodes[node];
+        }
+
+        // Partial overlap
+        self.push(node);
+        let (left_id, right_id) = children(node);
+        let mid = middle(lo, hi);
+        let left = self.query_node(start, end, left_id, lo, mid);
+        let right = self.query_node(start, end, right_id, mid + 1, hi);
+
+        M::combine(&left, &right)
+    }
+
+    /// `node` covers `[lo, hi]`.
+    fn apply_range(&mut self, start: usize, end: usize, tag: &A::Tag, node: usize, lo: usize, hi: usize) {
+        // No overlap
+        if lo > end || hi < start {
+            return;
+        }
+
+        // Full overlap
+        if lo >= start && hi <= end {
+            self.apply_tag(node, tag);
+            return;
+        }
+
+        // Partial overlap
+        self.push(node);
+        let (left_id, right_id) = children(node);
+        let mid = middle(lo, hi);
+
+        self.apply_range(start, end, tag, left_id, lo, mid);
+        self.apply_range(start, end, tag, right_id, mid + 1, hi);
+
+        self.nodes[node] = M::combine(&self.nodes[left_id], &self.nodes[right_id]);
+    }
+
+    /// Applies `tag` to the value of `node` and records it for its children.
+    /// The tag of a leaf is recorded too, but never pushed.
+    fn apply_tag(&mut self, node: usize, tag: &A::Tag) {
+        self.nodes[node] = A::apply(tag, &self.nodes[node]);
+
+        self.lazy_nodes[node] = Some(match &self.lazy_nodes[node] {
+            Some(pending) => A::compose(tag, pending),
+            None => *tag,
+        });
+    }
+
+    /// Pushes the pending tag of `node` down to its children.
+    fn push(&mut self, node: usize) {
+        if let Some(tag) = self.lazy_nodes[node].take() {
+            let (left_id, right_id) = children(node);
+
+            self.apply_tag(left_id, &tag);
+            self.apply_tag(right_id, &tag);
+        }
+    }
+}
+
+/// Returns the children of `node` in the layout of `build_nodes`.
+pub(crate) fn children(node: usize) -> (usize, usize) {
+    (2 * node + 1, 2 * node + 2)
+}
+
+/// Returns the last position of the left child of a node covering `[lo, hi]`.
+pub(crate) fn middle(lo: usize, hi: usize) -> usize {
+    lo + (hi - lo) / 2
+}
+
+/// Builds the nodes of a segment tree over `values` in O(n), with `combine` giving
+/// the value of a node from the values of its children.
+///
+/// The nodes are a flat array: the root is node 0, covering all the values, and
+/// the children of node `k`, see `children`, cover the two halves of its range,
+/// split at `middle`. The ranges are never stored, and the array has less than
+/// `4n` nodes, some of them unused.
+///
+/// # Panics
+/// Panics if `values` is empty.
+pub(crate) fn build_nodes<V: Copy>(values: &[V], combine: impl Fn(&V, &V) -> V) -> Vec<V> {
+    assert!(!values.is_empty(), "The segment tree needs at least one value");
+
+    let mut nodes: Vec<V> = vec![values[0]; 2 * values.len().next_power_of_two() - 1];
+    build_node(&mut nodes, values, &combine, 0, 0, values.len() - 1);
+
+    nodes
+}
+
+/// Fills the subtree of `node`, which covers `[lo, hi]`.
+fn build_node<V: Copy>(
+    nodes: &mut [V],
+    values: &[V],
+    combine: &impl Fn(&V, &V) -> V,
+    node: usize,
+    lo: usize,
+    hi: usize,
+) {
+    if lo == hi {
+        nodes[node] = values[lo];
+        return;
+    }
+
+    let (left_id, right_id) = children(node);
+    let mid = middle(lo, hi);
+    build_node(nodes, values, combine, left_id, lo, mid);
+    build_node(nodes, values, combine, right_id, mid + 1, hi);
+
+    nodes[node] = combine(&nodes[left_id], &nodes[right_id]);
+}
+
+#[cfg(test)]
+mod lazy_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    struct Sum;
+
+    impl Monoid for Sum {
+        type Value = (i64, i64); // (sum, length)
+
+        fn identity() -> (i64, i64) {
+            (0, 0)
+        }
+
+        fn combine(left: &(i64, i64), right: &(i64, i64)) -> (i64, i64) {
+            (left.0 + right.0, left.1 + right.1)
+        }
+    }
+
+    /// x -> a * x + b
+    struct Affine;
+
+    impl Action<Sum> for Affine {
+        type Tag = (i64, i64);
+
+        fn apply(tag: &(i64, i64), value: &(i64, i64)) -> (i64, i64) {
+            (tag.0 * value.0 + tag.1 * value.1, value.1)
+        }
+
+        fn compose(outer: &(i64, i64), inner: &(i64, i64)) -> (i64, i64) {
+            (outer.0 * inner.0, outer.0 * inner.1 + outer.1)
+        }
+    }
+
+    #[test]
+    fn random_against_naive() {
+        let mut rng = Rng::new(39);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(40);
+            let mut naive: Vec<i64> = (0..n).map(|_| rng.key(-5..=5) as i64).collect();
+            let values: Vec<(i64, i64)> = naive.iter().map(|&x| (x, 1)).collect();
+            let mut tree: LazySegTree<Sum, Affine> = LazySegTree::new(&values);
+            assert_eq!(tree.len(), n);
+
+            for _ in 0..100 {
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+
+                if rng.coin() {
+                    let tag = (rng.key(-1..=1) as i64, rng.key(-5..=5) as i64);
+                    tree.range_apply(start, end, tag);
+                    for x in &mut naive[start..=end] {
+                        *x = tag.0 * *x + tag.1;
+                    }
+                } else {
+                    let expected: i64 = naive[start..=end].iter().sum();
+                    assert_eq!(tree.range_query(start, end), (expected, (end - start + 1) as i64));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_against_naive() {
+        let mut rng = Rng::new(50);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(40);
+            let mut naive: Vec<i64> = (0..n).map(|_| rng.key(0..=5) as i64).collect();
+            let values: Vec<(i64, i64)> = naive.iter().map(|&x| (x, 1)).collect();
+            let mut tree: LazySegTree<Sum, Affine> = LazySegTree::new(&values);
+
+            for _ in 0..100 {
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+
+                if rng.coin() {
+                    // Keeps the values non-negative, so that a positive sum means a positive value
+                    let tag = (rng.key(0..=1) as i64, rng.key(0..=3) as i64);
+                    tree.range_apply(start, end, tag);
+                    naive[start..=end].iter_mut().for_each(|x| *x = tag.0 * *x + tag.1);
+                } else {
+                    let positive = |value: &(i64, i64)| value.0 > 0;
+                    assert_eq!(tree.find_first(start, positive), (start..n).find(|&i| naive[i] > 0));
+                    assert_eq!(tree.find_last(end, positive), (0..=end).rev().find(|&i| naive[i] > 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_bounds_is_identity() {
+        let mut tree: LazySegTree<Sum, Affine> = LazySegTree::new(&[(1, 1), (2, 1), (3, 1)]);
+
+        assert_eq!(tree.range_query(3, 10), (0, 0));
+        assert_eq!(tree.range_query(2, 1), (0, 0));
+        assert_eq!(tree.range_query(1, 10), (5, 2));
+    }
+}
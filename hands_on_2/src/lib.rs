@@ -1,402 +1,1200 @@
+pub mod beats;
+pub mod compressed;
+pub mod iterative;
+pub mod lazy;
+pub mod persistent;
+pub mod sparse;
+#[cfg(test)]
+mod random;
+
+use lazy::{Action, LazySegTree, Monoid};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+/// Minimum and maximum of `i32` values.
+pub struct Extremes;
+
+impl Monoid for Extremes {
+    type Value = (i32, i32);
+
+    fn identity() -> (i32, i32) {
+        (i32::MAX, i32::MIN)
+    }
 
-pub struct Range {
-    start: usize,
-    end: usize,
-}
-
-impl Range {
-    fn new(start: usize, end: usize) -> Self {
-        Self { start, end }
+    fn combine(left: &(i32, i32), right: &(i32, i32)) -> (i32, i32) {
+        (left.0.min(right.0), left.1.max(right.1))
     }
 }
 
-pub struct RangeNode {
-    key: i32,
-    range: Range,
-    id_left: Option<usize>,
-    id_right: Option<usize>,
+/// An update of `MinMax`. Every sequence of updates is one of these two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    /// Sets every value to the same value.
+    Assign(i32),
+    /// Sets every value `x` to `min(x + add, cap)`, with `cap == i64::MAX` for no cap.
+    /// Both are `i64` and compose with saturating arithmetic, so composing many of them
+    /// doesn't overflow: an `add` that saturates takes every value past `cap` anyway.
+    AddCap { add: i64, cap: i64 },
 }
 
-impl RangeNode {
-    fn new(key: i32, range: Range) -> Self {
-        Self {
-            key,
-            range,
-            id_left: None,
-            id_right: None,
+impl Action<Extremes> for Update {
+    type Tag = Update;
+
+    fn apply(update: &Update, value: &(i32, i32)) -> (i32, i32) {
+        match *update {
+            Update::Assign(x) => (x, x),
+            // A non-decreasing function keeps the minimum and the maximum in place
+            Update::AddCap { add, cap } => {
+                let f = |x: i32| to_i32((x as i64).saturating_add(add).min(cap));
+                (f(value.0), f(value.1))
+            }
         }
     }
 
-    pub fn clone_node(&self) -> RangeNode {
-        RangeNode::new(self.key, Range::new(self.range.start, self.range.end))
+    fn compose(outer: &Update, inner: &Update) -> Update {
+        match (*outer, *inner) {
+            (Update::Assign(x), _) => Update::Assign(x),
+            (Update::AddCap { add, cap }, Update::Assign(x)) => {
+                Update::Assign(to_i32((x as i64).saturating_add(add).min(cap)))
+            }
+            // min(min(x + a1, c1) + a2, c2) = min(x + a1 + a2, min(c1 + a2, c2))
+            (Update::AddCap { add: a2, cap: c2 }, Update::AddCap { add: a1, cap: c1 }) => {
+                let cap = match c1 {
+                    i64::MAX => c2,
+                    c1 => c1.saturating_add(a2).min(c2),
+                };
+
+                Update::AddCap { add: a1.saturating_add(a2), cap }
+            }
+        }
     }
 }
 
-pub struct LevelNode {
-    range_node: RangeNode,
-    level: usize,
+/// Narrows the result of an `Update`, which `MinMax::update` checks before applying it.
+fn to_i32(x: i64) -> i32 {
+    i32::try_from(x).expect("The update takes a value out of the i32 range")
 }
 
-impl LevelNode {
-    fn new(range_node: RangeNode, l: usize) -> Self {
-        Self {
-            range_node: range_node.clone_node(),
-            level: l,
+/// Error for a range of positions that can't be queried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// The range contains no positions.
+    Empty,
+    /// The range ends past the last position: `end` is the exclusive end.
+    OutOfBounds { end: usize, len: usize },
+    /// The update would take some value of the range out of the `i32` range.
+    Overflow,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::Empty => write!(f, "the range is empty"),
+            RangeError::OutOfBounds { end, len } => {
+                write!(f, "the range ends at {} but the length is {}", end, len)
+            }
+            RangeError::Overflow => write!(f, "the update takes a value out of the i32 range"),
         }
     }
+}
 
-    pub fn clone_node(&self) -> LevelNode {
-        LevelNode::new(self.range_node.clone_node(), self.level)
+impl std::error::Error for RangeError {}
+
+/// Converts `range` of 0-based positions into the inclusive bounds used by the trees,
+/// checking it against `len` positions.
+fn inclusive_bounds(
+    range: &impl RangeBounds<usize>,
+    len: usize,
+) -> Result<(usize, usize), RangeError> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => Some(start),
+        Bound::Excluded(&start) => start.checked_add(1),
+        Bound::Unbounded => Some(0),
+    };
+    // Exclusive end, `None` if it doesn't fit in a usize
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1),
+        Bound::Excluded(&end) => Some(end),
+        Bound::Unbounded => Some(len),
+    };
+
+    match (start, end) {
+        (None, _) => Err(RangeError::Empty),
+        (_, None) => Err(RangeError::OutOfBounds { end: usize::MAX, len }),
+        (Some(start), Some(end)) if start >= end => Err(RangeError::Empty),
+        (Some(_), Some(end)) if end > len => Err(RangeError::OutOfBounds { end, len }),
+        (Some(start), Some(end)) => Ok((start, end - 1)),
     }
 }
 
-pub struct MinMax {
-    nodes: Vec<RangeNode>,
-    lazy_nodes: Vec<Option<i32>>,
+/// An operation on `MinMax`, to run many of them with `MinMax::process`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    /// Sets every value `x` in the range to `min(x, t)`.
+    ChMin { range: (Bound<usize>, Bound<usize>), t: i32 },
+    /// Adds `delta` to every value in the range.
+    Add { range: (Bound<usize>, Bound<usize>), delta: i32 },
+    /// Sets every value in the range to `value`.
+    Assign { range: (Bound<usize>, Bound<usize>), value: i32 },
+    /// Sets the value at `index` to `value`.
+    Set { index: usize, value: i32 },
+    /// Returns the maximum in the range.
+    Max { range: (Bound<usize>, Bound<usize>) },
+    /// Returns the minimum in the range.
+    Min { range: (Bound<usize>, Bound<usize>) },
 }
 
-fn tree_init(arr: &Vec<i32>, start: usize, end: usize, level: usize) -> (Vec<LevelNode>, i32) {
-    if start == end {
-        return (
-            vec![LevelNode::new(
-                RangeNode::new(arr[start], Range::new(start, end)),
-                level,
-            )],
-            arr[start],
-        );
+impl Query {
+    pub fn chmin(range: impl RangeBounds<usize>, t: i32) -> Self {
+        Query::ChMin {
+            range: bounds(range),
+            t,
+        }
     }
 
-    let mid = (start + end) / 2;
-    let (left_nodes, left_max) = tree_init(arr, start, mid, level + 1);
-    let (right_nodes, right_max) = tree_init(arr, mid + 1, end, level + 1);
-    let maximum: i32 = i32::max(left_max, right_max);
+    pub fn add(range: impl RangeBounds<usize>, delta: i32) -> Self {
+        Query::Add {
+            range: bounds(range),
+            delta,
+        }
+    }
 
-    let mut ret_arr: Vec<LevelNode> = Vec::with_capacity((left_nodes.len() + right_nodes.len()) + 1);
-    ret_arr.push(LevelNode::new(
-        RangeNode::new(maximum, Range::new(start, end)),
-        level,
-    ));
-    merge(&left_nodes, &right_nodes, &mut ret_arr);
+    pub fn assign(range: impl RangeBounds<usize>, value: i32) -> Self {
+        Query::Assign {
+            range: bounds(range),
+            value,
+        }
+    }
 
-    (ret_arr, maximum)
-}
+    pub fn set(index: usize, value: i32) -> Self {
+        Query::Set { index, value }
+    }
 
-fn merge(left: &[LevelNode], right: &[LevelNode], merged_arr: &mut Vec<LevelNode>) {
-    let mut i = 0;
-    let mut j = 0;
+    pub fn max(range: impl RangeBounds<usize>) -> Self {
+        Query::Max {
+            range: bounds(range),
+        }
+    }
 
-    while i < left.len() && j < right.len() {
-        if left[i].level <= right[j].level {
-            merged_arr.push(left[i].clone_node());
-            i += 1;
-        } else {
-            merged_arr.push(right[j].clone_node());
-            j += 1;
+    pub fn min(range: impl RangeBounds<usize>) -> Self {
+        Query::Min {
+            range: bounds(range),
+        }
+    }
+
+    /// Returns the positions affected by the query.
+    fn range(&self) -> (Bound<usize>, Bound<usize>) {
+        match *self {
+            Query::ChMin { range, .. }
+            | Query::Add { range, .. }
+            | Query::Assign { range, .. }
+            | Query::Max { range }
+            | Query::Min { range } => range,
+            Query::Set { index, .. } => (Bound::Included(index), Bound::Included(index)),
         }
     }
+}
 
-    merged_arr.extend(left[i..].iter().map(LevelNode::clone_node));
-    merged_arr.extend(right[j..].iter().map(LevelNode::clone_node));
+fn bounds(range: impl RangeBounds<usize>) -> (Bound<usize>, Bound<usize>) {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+/// Range updates and range minimum and maximum queries over `i32` values.
+///
+/// Updates compose into a single lazy `Update`, so any mix of them costs O(log n).
+/// An update that would take some value out of the range of `i32` changes nothing
+/// and returns `RangeError::Overflow`.
+pub struct MinMax {
+    tree: LazySegTree<Extremes, Update>,
 }
 
 impl MinMax {
-    /* Complexity = O(n log n) used merge sort to build the segment tree*/
     pub fn new(arr: Vec<i32>) -> Self {
-        let (ranges, _) = tree_init(&arr, 0, arr.len() - 1, 0);
-        let mut lazy_tree: Vec<Option<i32>> = Vec::with_capacity(ranges.len());
-        let mut seg_tree: Vec<RangeNode> = Vec::with_capacity(ranges.len());
-
-        let mut nonode = 0;
-
-        for (i, node) in ranges.iter().enumerate() {
-            let mut new_node = node.range_node.clone_node();
-
-            if new_node.range.end - new_node.range.start == 0 {
-                new_node.id_left = None;
-                new_node.id_right = None;
-                nonode += 1;
-            } else {
-                new_node.id_left = Some(((i - nonode) * 2) + 1);
-                new_node.id_right = Some(((i - nonode) * 2) + 2);
-            }
-
-            seg_tree.push(new_node);
-            lazy_tree.push(None);
-        }
+        let values: Vec<(i32, i32)> = arr.iter().map(|&x| (x, x)).collect();
 
         Self {
-            nodes: seg_tree,
-            lazy_nodes: lazy_tree,
+            tree: LazySegTree::new(&values),
         }
     }
 
-    /* complexity of one query O(log n), total queries m -> O(m * log n) total complexity O((n + m) * log n) */
-    pub fn query(&mut self, query: usize, start: usize, end: usize, t: i32) -> Option<i32> {
-        if query == 0 {
-            self.update(start - 1, end - 1, t, Some(0));
-        }
-        self.max(start - 1, end - 1, Some(0))
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.tree.len()
     }
 
-    fn max(&mut self, start: usize, end: usize, node: Option<usize>) -> Option<i32> {
-        if let Some(node) = node {
-            self.update_node(node);
-
-            if self.nodes[node].range.start > end || self.nodes[node].range.end < start {
-                return None;
-            }
-
-            if self.nodes[node].range.start >= start && self.nodes[node].range.end <= end {
-                return Some(self.nodes[node].key);
-            }
+    /// Always `false`, since `MinMax` can't be built over no values.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
 
-            if self.nodes[node].range.start <= start || self.nodes[node].range.end >= end {
-                let max_left = self.max(start, end, self.nodes[node].id_left);
-                let max_right = self.max(start, end, self.nodes[node].id_right);
+    /// Sets every value `x` in `range` to `min(x, t)`. Positions are 0-based. O(log n).
+    pub fn chmin(&mut self, range: impl RangeBounds<usize>, t: i32) -> Result<(), RangeError> {
+        self.update(range, Update::AddCap { add: 0, cap: t as i64 })
+    }
 
-                if max_left.is_none() && max_right.is_none() {
-                    return Some(self.nodes[node].key);
-                }
+    /// Adds `delta` to every value in `range`. Positions are 0-based. O(log n).
+    pub fn add(&mut self, range: impl RangeBounds<usize>, delta: i32) -> Result<(), RangeError> {
+        self.update(range, Update::AddCap { add: delta as i64, cap: i64::MAX })
+    }
 
-                if max_left.is_none() {
-                    return max_right;
-                }
+    /// Sets every value in `range` to `value`. Positions are 0-based. O(log n).
+    pub fn assign(&mut self, range: impl RangeBounds<usize>, value: i32) -> Result<(), RangeError> {
+        self.update(range, Update::Assign(value))
+    }
 
-                if max_right.is_none() {
-                    return max_left;
-                }
+    /// Sets the value at `index` to `value`. Positions are 0-based. O(log n).
+    pub fn set(&mut self, index: usize, value: i32) -> Result<(), RangeError> {
+        self.assign(index..=index, value)
+    }
 
-                return std::cmp::max(max_left, max_right);
+    /// Applies `update` to every value in `range`. Positions are 0-based. O(log n).
+    pub fn update(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        update: Update,
+    ) -> Result<(), RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len())?;
+
+        // The update is non-decreasing, so the new extremes come from the old ones
+        if let Update::AddCap { add, cap } = update {
+            let (min, max) = self.tree.range_query(start, end);
+            let f = |x: i32| (x as i64).saturating_add(add).min(cap);
+
+            if i32::try_from(f(min)).is_err() || i32::try_from(f(max)).is_err() {
+                return Err(RangeError::Overflow);
             }
         }
+        self.tree.range_apply(start, end, update);
 
-        None
+        Ok(())
     }
 
-    fn update_node(&mut self, node: usize) {
-        if let Some(new_val) = self.lazy_nodes[node] {
-            if new_val <= self.nodes[node].key {
-                self.nodes[node].key = new_val;
+    /// Returns the maximum of the values in `range`. Positions are 0-based. O(log n).
+    pub fn max(&mut self, range: impl RangeBounds<usize>) -> Result<i32, RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len())?;
 
-                self.propagate(node, new_val);
+        Ok(self.tree.range_query(start, end).1)
+    }
 
-                self.lazy_nodes[node] = None;
-            }
-        }
+    /// Returns the minimum of the values in `range`. Positions are 0-based. O(log n).
+    pub fn min(&mut self, range: impl RangeBounds<usize>) -> Result<i32, RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len())?;
+
+        Ok(self.tree.range_query(start, end).0)
     }
 
-    fn propagate(&mut self, node: usize, t: i32) {
-        if self.nodes[node].id_left.is_none() {
-            return;
-        }
+    /// Returns the position of the maximum in `range`, the leftmost one if the
+    /// maximum is in many positions. Positions are 0-based. O(log n).
+    pub fn argmax(&mut self, range: impl RangeBounds<usize>) -> Result<usize, RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len())?;
+        let max = self.tree.range_query(start, end).1;
 
-        let left_id = self.nodes[node].id_left.unwrap();
-        let right_id = self.nodes[node].id_right.unwrap();
+        // The maximum is in the range, so no position past the end is found before
+        Ok(self.tree.find_first(start, |&(_, m)| m >= max).unwrap())
+    }
+
+    /// Returns the first position `i >= l` whose value satisfies `pred`, or `None`
+    /// if there is none. Positions are 0-based. O(log n).
+    ///
+    /// `pred` gets the minimum and the maximum of a range and must hold iff it holds
+    /// for some value in the range, like `|_, max| max >= x` for the first value
+    /// not below `x`, or `|min, _| min <= x` for the first value not above `x`.
+    pub fn find_first(&mut self, l: usize, pred: impl Fn(i32, i32) -> bool) -> Option<usize> {
+        self.tree.find_first(l, |&(min, max)| pred(min, max))
+    }
 
-        self.lazy_nodes[left_id] = Some(t);
-        self.lazy_nodes[right_id] = Some(t);
+    /// Returns the last position `i <= r` whose value satisfies `pred`, or `None`
+    /// if there is none. Positions are 0-based. O(log n). `pred` must be as in `find_first`.
+    pub fn find_last(&mut self, r: usize, pred: impl Fn(i32, i32) -> bool) -> Option<usize> {
+        self.tree.find_last(r, |&(min, max)| pred(min, max))
     }
 
-    fn update(&mut self, start: usize, end: usize, t: i32, node: Option<usize>) -> Option<i32> {
-        if let Some(node) = node {
-            self.update_node(node);
+    /// Runs `queries` in order and returns the results of the `Query::Max` and
+    /// `Query::Min` ones. If any range is invalid, returns its error without
+    /// running any query. An update that overflows stops the run with its error,
+    /// after the queries before it.
+    pub fn process(&mut self, queries: &[Query]) -> Result<Vec<i32>, RangeError> {
+        for query in queries {
+            inclusive_bounds(&query.range(), self.len())?;
+        }
 
-            // Nessuna sovrapposizione
-            if self.nodes[node].range.start > end || self.nodes[node].range.end < start {
-                return Some(self.nodes[node].key);
+        let mut results: Vec<i32> = Vec::new();
+        for query in queries {
+            match *query {
+                Query::ChMin { range, t } => self.chmin(range, t)?,
+                Query::Add { range, delta } => self.add(range, delta)?,
+                Query::Assign { range, value } => self.assign(range, value)?,
+                Query::Set { index, value } => self.set(index, value)?,
+                Query::Max { range } => results.push(self.max(range)?),
+                Query::Min { range } => results.push(self.min(range)?),
             }
+        }
 
-            // Sovrapposizione completa
-            if self.nodes[node].range.start >= start && self.nodes[node].range.end <= end {
-                if self.nodes[node].key > t {
-                    self.nodes[node].key = t;
-                    self.propagate(node, t);
-                }
+        Ok(results)
+    }
 
-                return Some(self.nodes[node].key);
-            }
+    /// Legacy interface of the testset, with 1-based positions: `query == 0`
+    /// runs `chmin(start..=end, t)` first, then every query returns the maximum.
+    /// Prefer `chmin`, `max` and `process`.
+    /* complexity of one query O(log n), total queries m -> O(m * log n) total complexity O((n + m) * log n) */
+    pub fn query(&mut self, query: usize, start: usize, end: usize, t: i32) -> Option<i32> {
+        let range = start.checked_sub(1)?..end;
 
-            // Sovrapposizione parziale
-            if self.nodes[node].range.start <= start || self.nodes[node].range.end >= end {
-                let left_id = self.nodes[node].id_left;
-                let right_id = self.nodes[node].id_right;
+        if query == 0 {
+            self.chmin(range.clone(), t).ok()?;
+        }
+        self.max(range).ok()
+    }
+}
 
-                let max_left = self.update(start, end, t, left_id).unwrap();
-                let max_right = self.update(start, end, t, right_id).unwrap();
+/// Bitwise or of `u128` values.
+struct Or;
 
-                let max: i32 = std::cmp::max(max_left, max_right);
+impl Monoid for Or {
+    type Value = u128;
 
-                if max != self.nodes[node].key {
-                    self.nodes[node].key = max;
-                }
+    fn identity() -> u128 {
+        0
+    }
 
-                return Some(self.nodes[node].key);
-            }
+    fn combine(left: &u128, right: &u128) -> u128 {
+        left | right
+    }
+}
+
+/// Shifts every value left by the number of bits in the tag, right if negative.
+/// Nothing is lost as long as the bits stay inside the `u128`.
+struct Shift;
+
+impl Action<Or> for Shift {
+    type Tag = isize;
+
+    fn apply(bits: &isize, value: &u128) -> u128 {
+        if *bits >= 0 {
+            value << bits
+        } else {
+            value >> -bits
         }
+    }
 
-        None
+    fn compose(outer: &isize, inner: &isize) -> isize {
+        outer + inner
     }
 }
 
-pub struct IsThere {
-    nodes: Vec<URangeNode>,
-    lazy_nodes: Vec<u128>,
+/// Coverages of a range of positions: the lowest and the highest, the total weight
+/// of the positions holding each, and the bitmask of the coverages in it among
+/// `min..min + WINDOW`, bit `c` for the coverage `min + c`.
+#[derive(Clone, Copy)]
+struct CoverageSummary {
+    min: usize,
+    max: usize,
+    min_weight: u64,
+    max_weight: u64,
+    window: u128,
 }
 
-fn tree_init_zero(start: usize, end: usize, level: usize) -> Vec<LevelNode> {
-    if start == end {
-        return vec![LevelNode::new(
-            RangeNode::new(1, Range::new(start, end)),
-            level,
-        )];
+/// Number of coverages tracked by a bitmask
+const WINDOW: usize = u128::BITS as usize;
+
+impl CoverageSummary {
+    fn leaf(coverage: usize, weight: u64) -> Self {
+        Self {
+            min: coverage,
+            max: coverage,
+            min_weight: weight,
+            max_weight: weight,
+            window: 1,
+        }
     }
 
-    let mid = (start + end) / 2;
-    let left_seg_tree = tree_init_zero(start, mid, level + 1);
-    let right_seg_tree = tree_init_zero(mid + 1, end, level + 1);
+    /// Returns if some position has coverage `k`, or `None` if `k` is between the
+    /// minimum and the maximum but past the window. Always `Some` for a position.
+    fn holds(&self, k: usize) -> Option<bool> {
+        if k < self.min || k > self.max {
+            Some(false)
+        } else if k == self.min || k == self.max {
+            Some(true)
+        } else if k - self.min < WINDOW {
+            Some(self.window >> (k - self.min) & 1 == 1)
+        } else {
+            None
+        }
+    }
+}
 
-    let mut ret_arr: Vec<LevelNode> = Vec::with_capacity((2 * left_seg_tree.len()) + 1);
-    ret_arr.push(LevelNode::new(
-        RangeNode::new(1, Range::new(start, end)),
-        level,
-    ));
-    merge(&left_seg_tree, &right_seg_tree, &mut ret_arr);
+/// Combines the coverages of adjacent ranges.
+struct Coverages;
 
-    ret_arr
-}
+impl Monoid for Coverages {
+    type Value = CoverageSummary;
+
+    fn identity() -> CoverageSummary {
+        CoverageSummary {
+            min: usize::MAX,
+            max: 0,
+            min_weight: 0,
+            max_weight: 0,
+            window: 0,
+        }
+    }
 
-pub struct URangeNode {
-    key: u128,
-    range: Range,
-    id_left: Option<usize>,
-    id_right: Option<usize>,
+    /// The identity needs no special case: its minimum is above any other, its
+    /// maximum below, and its weights and bitmask are empty.
+    fn combine(left: &CoverageSummary, right: &CoverageSummary) -> CoverageSummary {
+        let (min, max) = (left.min.min(right.min), left.max.max(right.max));
+        let weight = |k: usize, coverage: usize, weight: u64| if k == coverage { weight } else { 0 };
+        // The coverages past the window of the combined range are dropped
+        let window = |summary: &CoverageSummary| match summary.min - min {
+            shift if shift < WINDOW => summary.window << shift,
+            _ => 0,
+        };
+
+        CoverageSummary {
+            min,
+            max,
+            min_weight: weight(min, left.min, left.min_weight) + weight(min, right.min, right.min_weight),
+            max_weight: weight(max, left.max, left.max_weight) + weight(max, right.max, right.max_weight),
+            window: window(left) | window(right),
+        }
+    }
 }
 
-impl URangeNode {
-    fn new(key: u128, range: Range) -> Self {
-        Self {
-            key,
-            range,
-            id_left: None,
-            id_right: None,
+/// Adds the number of segments in the tag to every coverage, negative to remove them.
+struct Cover;
+
+impl Action<Coverages> for Cover {
+    type Tag = isize;
+
+    /// Only added segments are removed, so no coverage goes below 0.
+    fn apply(segments: &isize, summary: &CoverageSummary) -> CoverageSummary {
+        CoverageSummary {
+            min: summary.min.wrapping_add_signed(*segments),
+            max: summary.max.wrapping_add_signed(*segments),
+            ..*summary
         }
     }
 
-    pub fn clone_node(&self) -> URangeNode {
-        URangeNode::new(self.key, Range::new(self.range.start, self.range.end))
+    fn compose(outer: &isize, inner: &isize) -> isize {
+        outer + inner
     }
 }
 
+/// The segment tree of `IsThere`.
+enum CoverageTree {
+    /// The value of a position has the bit `c` set when `c` segments cover it, so
+    /// the value of a range has the bits of all the coverages in it. It only holds
+    /// coverages up to 127.
+    Bitmask(LazySegTree<Or, Shift>),
+    /// Summaries of the coverages, for any coverage.
+    Summary(LazySegTree<Coverages, Cover>),
+}
+
+/// Positions covered by some segments, to find positions covered by exactly `k` of them.
+///
+/// While every coverage is below 128, a `LazySegTree` keeps the bitmask of the coverages
+/// of every range, so adding or removing a segment and looking for `k` cost O(log n).
+/// Once some coverage is 127, the next segment moves the coverages, in O(n log n), to
+/// a `LazySegTree` whose nodes have the lowest and the highest coverage of their range,
+/// with the weight of the positions holding them, and the bitmask of the coverages
+/// within 127 of the lowest. There a segment still costs O(log n), and so does looking
+/// for `k` while the nodes visited have it in their bitmask, or as their lowest or
+/// highest coverage. A node with `k` past its bitmask is searched further down, up to
+/// O(n) in the worst case.
+/// Positions are 0-based and ranges are inclusive.
+pub struct IsThere {
+    tree: CoverageTree,
+    /// Number of times each segment has been added and not removed
+    segments: BTreeMap<(usize, usize), usize>,
+    /// Number of integer points in every position, all 1 if `None`.
+    /// The positions are cells of many points in `compressed::CompressedIsThere`.
+    weights: Option<Vec<u64>>,
+}
+
 impl IsThere {
+    /// Builds the structure over `interval` positions, none of them covered.
+    ///
+    /// # Panics
+    /// Panics if `interval` is 0.
     pub fn new(interval: u128) -> Self {
-        let ranges = tree_init_zero(0, (interval as usize) - 1, 0);
-        let mut lazy_tree: Vec<u128> = Vec::with_capacity(ranges.len());
-        let mut seg_tree: Vec<URangeNode> = Vec::with_capacity(ranges.len());
-
-        let mut nonode = 0;
-
-        for (i, node) in ranges.iter().enumerate() {
-            let mut new_node = URangeNode::new(
-                1,
-                Range::new(node.range_node.range.start, node.range_node.range.end),
-            );
-
-            if new_node.range.end - new_node.range.start == 0 {
-                new_node.id_left = None;
-                new_node.id_right = None;
-                nonode += 1;
-            } else {
-                new_node.id_left = Some(((i - nonode) * 2) + 1);
-                new_node.id_right = Some(((i - nonode) * 2) + 2);
-            }
+        Self::build(interval as usize, None)
+    }
 
-            seg_tree.push(new_node);
-            lazy_tree.push(0);
-        }
+    /// Builds the structure over `n` positions, with the weights of `weights` if any.
+    fn build(n: usize, weights: Option<Vec<u64>>) -> Self {
+        assert!(n > 0, "There must be at least one position");
 
         Self {
-            nodes: seg_tree,
-            lazy_nodes: lazy_tree,
+            tree: CoverageTree::Bitmask(LazySegTree::new(&vec![1; n])),
+            segments: BTreeMap::new(),
+            weights,
         }
     }
 
-    fn update_node(&mut self, node: usize) {
-        if self.lazy_nodes[node] > 0 {
-            self.nodes[node].key <<= self.lazy_nodes[node];
-
-            self.propagate(node, self.lazy_nodes[node]);
-            self.lazy_nodes[node] = 0;
+    /// Returns the number of positions.
+    pub fn len(&self) -> usize {
+        match &self.tree {
+            CoverageTree::Bitmask(tree) => tree.len(),
+            CoverageTree::Summary(tree) => tree.len(),
         }
     }
 
-    fn propagate(&mut self, node: usize, t: u128) {
-        if self.nodes[node].id_left.is_none() {
+    /// Always `false`, since there is at least one position.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Adds the segment `[i, j]`, whose positions past the end are ignored.
+    pub fn add_segment(&mut self, i: usize, j: usize) {
+        let Some((i, j)) = self.clamp(i, j) else {
             return;
+        };
+
+        *self.segments.entry((i, j)).or_insert(0) += 1;
+
+        // The root has all the coverages, at once since it's the whole range
+        if let CoverageTree::Bitmask(tree) = &mut self.tree {
+            if tree.range_query(0, tree.len() - 1) >> (WINDOW - 1) == 0 {
+                tree.range_apply(i, j, 1);
+                return;
+            }
+            self.switch_to_summary();
         }
+        if let CoverageTree::Summary(tree) = &mut self.tree {
+            tree.range_apply(i, j, 1);
+        }
+    }
 
-        let left_id = self.nodes[node].id_left.unwrap();
-        let right_id = self.nodes[node].id_right.unwrap();
+    /// Removes a segment `[i, j]` added before, whose positions past the end are
+    /// ignored like in `add_segment`. Returns `false`, changing nothing, if there
+    /// is no such segment.
+    pub fn remove_segment(&mut self, i: usize, j: usize) -> bool {
+        let j = j.min(self.len() - 1);
+        let Some(count) = self.segments.get_mut(&(i, j)) else {
+            return false;
+        };
+
+        *count -= 1;
+        if *count == 0 {
+            self.segments.remove(&(i, j));
+        }
+        match &mut self.tree {
+            CoverageTree::Bitmask(tree) => tree.range_apply(i, j, -1),
+            CoverageTree::Summary(tree) => tree.range_apply(i, j, -1),
+        }
 
-        self.lazy_nodes[left_id] += t;
-        self.lazy_nodes[right_id] += t;
+        true
     }
 
+    /// Returns `true` iff some position in `[i, j]` is covered by exactly `k` segments.
+    pub fn is_there(&mut self, i: usize, j: usize, k: usize) -> bool {
+        let Some((i, j)) = self.clamp(i, j) else {
+            return false;
+        };
+
+        let holds = match &mut self.tree {
+            CoverageTree::Bitmask(tree) => Some(k < WINDOW && tree.range_query(i, j) >> k & 1 == 1),
+            CoverageTree::Summary(tree) => tree.range_query(i, j).holds(k),
+        };
+
+        // Only a summary can be unsure, then the search goes down to the positions
+        holds.unwrap_or_else(|| self.first_exact(i, k).is_some_and(|position| position <= j))
+    }
+
+    /// Returns the number of positions in `[i, j]` covered by exactly `k` segments.
+    pub fn count_exact(&mut self, i: usize, j: usize, k: usize) -> usize {
+        self.weight_exact(i, j, k) as usize
+    }
+
+    /// Returns the total weight of the positions in `[i, j]` covered by exactly `k`
+    /// segments. Every position found costs O(log n), unless `k` is the lowest or
+    /// the highest coverage in the range of the summaries.
+    fn weight_exact(&mut self, i: usize, j: usize, k: usize) -> u64 {
+        let range = self.clamp(i, j);
+        if let (CoverageTree::Summary(tree), Some((i, j))) = (&mut self.tree, range) {
+            let summary = tree.range_query(i, j);
+
+            if k == summary.min {
+                return summary.min_weight;
+            } else if k == summary.max {
+                return summary.max_weight;
+            }
+        }
+
+        let positions = self.positions_exact(i, j, k);
+        positions.into_iter().map(|position| self.weight(position)).sum()
+    }
+
+    /// Returns the positions in `[i, j]` covered by exactly `k` segments, in
+    /// increasing order. Every position found costs O(log n) more.
+    pub fn positions_exact(&mut self, i: usize, j: usize, k: usize) -> Vec<usize> {
+        let mut positions: Vec<usize> = Vec::new();
+        if !self.is_there(i, j, k) {
+            return positions;
+        }
+
+        let mut start = i;
+        while let Some(position) = self.first_exact(start, k).filter(|&position| position <= j) {
+            positions.push(position);
+            start = position + 1;
+        }
+
+        positions
+    }
+
+    /// Legacy interface of the testset: `query == 1` returns 1 iff `is_there(i, j, k)`,
+    /// otherwise it runs `add_segment(i, j)` and returns 0.
     pub fn query(&mut self, query: usize, i: usize, j: usize, k: u128) -> i8 {
-        let p = 2u128.pow(k as u32);
         if query == 1 {
-            let ret = self.is_there(i, j, p, 0) as i8;
+            return usize::try_from(k).is_ok_and(|k| self.is_there(i, j, k)) as i8;
+        }
 
-            if ret > 0 {
-                return 1;
-            }
-            return 0;
+        self.add_segment(i, j);
+        0
+    }
+
+    /// Returns the first position from `start` on covered by exactly `k` segments.
+    /// The summaries that may have `k` past their bitmask are searched as well.
+    fn first_exact(&mut self, start: usize, k: usize) -> Option<usize> {
+        match &mut self.tree {
+            CoverageTree::Bitmask(tree) if k < WINDOW => tree.find_first(start, |bits| bits >> k & 1 == 1),
+            CoverageTree::Bitmask(_) => None,
+            CoverageTree::Summary(tree) => tree.find_first(start, |summary| summary.holds(k) != Some(false)),
         }
+    }
 
-        self.update(i, j, 0) as i8
+    /// Moves the coverages from the bitmask tree to the summary tree.
+    fn switch_to_summary(&mut self) {
+        if let CoverageTree::Bitmask(tree) = &mut self.tree {
+            let leaves: Vec<CoverageSummary> = (0..tree.len())
+                .map(|p| {
+                    let coverage = tree.range_query(p, p).trailing_zeros() as usize;
+                    CoverageSummary::leaf(coverage, self.weights.as_ref().map_or(1, |weights| weights[p]))
+                })
+                .collect();
+
+            self.tree = CoverageTree::Summary(LazySegTree::new(&leaves));
+        }
+    }
+
+    /// Returns `[i, j]` with the positions past the end dropped, `None` if empty.
+    fn clamp(&self, i: usize, j: usize) -> Option<(usize, usize)> {
+        let j = j.min(self.len() - 1);
+        (i <= j).then_some((i, j))
     }
 
-    fn is_there(&mut self, start: usize, end: usize, k: u128, node: usize) -> u128 {
-        self.update_node(node);
+    fn weight(&self, position: usize) -> u64 {
+        self.weights.as_ref().map_or(1, |weights| weights[position])
+    }
+}
+
+#[cfg(test)]
+mod legacy_query_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn min_max_against_naive() {
+        let mut rng = Rng::new(1);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(40);
+            let mut naive: Vec<i32> = (0..n).map(|_| rng.key(-50..=50)).collect();
+            let mut min_max = MinMax::new(naive.clone());
+
+            for _ in 0..100 {
+                // 1-based like the testset
+                let start = 1 + rng.below(n);
+                let end = start + rng.below(n - start + 1);
+
+                if rng.coin() {
+                    let t = rng.key(-50..=50);
+                    min_max.query(0, start, end, t);
+                    for x in &mut naive[start - 1..end] {
+                        *x = (*x).min(t);
+                    }
+                } else {
+                    let expected = naive[start - 1..end].iter().max().copied();
+                    assert_eq!(min_max.query(1, start, end, 0), expected);
+                }
+            }
+        }
+    }
 
-        if self.nodes[node].range.start > end || self.nodes[node].range.end < start {
-            return 0;
+    #[test]
+    fn is_there_against_naive() {
+        let mut rng = Rng::new(2);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(40);
+            let mut coverage: Vec<u128> = vec![0; n];
+            let mut is_there = IsThere::new(n as u128);
+
+            for _ in 0..60 {
+                let i = rng.below(n);
+                let j = i + rng.below(n - i);
+
+                if rng.coin() {
+                    is_there.query(0, i, j, 0);
+                    for c in &mut coverage[i..=j] {
+                        *c += 1;
+                    }
+                } else {
+                    let k = rng.below(8) as u128;
+                    let expected = coverage[i..=j].contains(&k) as i8;
+                    assert_eq!(is_there.query(1, i, j, k), expected);
+                }
+            }
         }
+    }
+}
+
+#[cfg(test)]
+mod query_api_tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let mut min_max = MinMax::new(vec![5, 1, 4, 2, 3]);
+
+        assert_eq!(min_max.max(..), Ok(5));
+        assert_eq!(min_max.max(1..3), Ok(4));
+        assert_eq!(min_max.max(1..=1), Ok(1));
+        assert_eq!(min_max.max(3..), Ok(3));
+        assert_eq!(min_max.max(..=0), Ok(5));
+        assert_eq!(min_max.max((Bound::Excluded(0), Bound::Excluded(2))), Ok(1));
+
+        assert_eq!(min_max.chmin(0..2, 0), Ok(()));
+        assert_eq!(min_max.max(..), Ok(4));
+    }
 
-        if self.nodes[node].range.start >= start && self.nodes[node].range.end <= end {
-            return self.nodes[node].key & k;
+    #[test]
+    fn invalid_ranges() {
+        let mut min_max = MinMax::new(vec![5, 1, 4]);
+
+        assert_eq!(min_max.max(2..2), Err(RangeError::Empty));
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 2..=1;
+        assert_eq!(min_max.max(reversed), Err(RangeError::Empty));
+        assert_eq!(min_max.max(1..4), Err(RangeError::OutOfBounds { end: 4, len: 3 }));
+        assert_eq!(min_max.max(3..=3), Err(RangeError::OutOfBounds { end: 4, len: 3 }));
+        assert_eq!(
+            min_max.max(0..=usize::MAX),
+            Err(RangeError::OutOfBounds { end: usize::MAX, len: 3 })
+        );
+        assert_eq!(min_max.chmin(0..4, 0), Err(RangeError::OutOfBounds { end: 4, len: 3 }));
+        assert_eq!(min_max.max(..), Ok(5));
+
+        assert_eq!(min_max.query(1, 0, 2, 0), None);
+        assert_eq!(RangeError::Empty.to_string(), "the range is empty");
+    }
+
+    #[test]
+    fn batch() {
+        let mut min_max = MinMax::new(vec![5, 1, 4, 2, 3]);
+
+        let queries = [
+            Query::max(..),
+            Query::chmin(0..=2, 3),
+            Query::max(0..3),
+            Query::max(3..5),
+        ];
+        assert_eq!(min_max.process(&queries), Ok(vec![5, 3, 3]));
+
+        // Nothing runs if a query is invalid
+        let queries = [Query::chmin(.., 0), Query::max(0..10)];
+        assert_eq!(
+            min_max.process(&queries),
+            Err(RangeError::OutOfBounds { end: 10, len: 5 })
+        );
+        assert_eq!(min_max.max(..), Ok(3));
+    }
+}
+
+#[cfg(test)]
+mod is_there_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn random_against_naive() {
+        let mut rng = Rng::new(43);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(60);
+            let mut coverage: Vec<usize> = vec![0; n];
+            let mut is_there = IsThere::new(n as u128);
+
+            for _ in 0..200 {
+                let i = rng.below(n);
+                let j = i + rng.below(n - i);
+
+                if rng.coin() {
+                    is_there.add_segment(i, j);
+                    coverage[i..=j].iter_mut().for_each(|c| *c += 1);
+                } else {
+                    let k = rng.below(100);
+                    assert_eq!(is_there.is_there(i, j, k), coverage[i..=j].contains(&k));
+                }
+            }
         }
+    }
 
-        if self.nodes[node].range.start <= start || self.nodes[node].range.end >= end {
-            let left_overlap = self.is_there(start, end, k, self.nodes[node].id_left.unwrap());
-            let right_overlap = self.is_there(start, end, k, self.nodes[node].id_right.unwrap());
+    #[test]
+    fn thousands_of_overlaps() {
+        let mut rng = Rng::new(44);
+        let n = 5000;
+        let mut is_there = IsThere::new(n as u128);
 
-            return right_overlap | left_overlap;
+        // Position p is covered by p + 1 segments
+        for j in 0..n {
+            is_there.add_segment(0, n - 1 - j);
+        }
+        for p in [0, 126, 127, 128, 129, 1000, n - 1] {
+            let k = n - p;
+            assert!(is_there.is_there(p, p, k));
+            assert!(!is_there.is_there(p, p, k + 1));
+            assert_eq!(is_there.query(1, 0, n - 1, k as u128), 1);
+        }
+        assert!(!is_there.is_there(0, n - 1, 0));
+        assert!(!is_there.is_there(0, n - 1, n + 1));
+
+        // Random segments, checked against the coverage counted directly
+        let mut coverage: Vec<usize> = (0..n).map(|p| n - p).collect();
+        for _ in 0..3000 {
+            let i = rng.below(n);
+            let j = i + rng.below(n - i);
+            is_there.add_segment(i, j);
+            coverage[i..=j].iter_mut().for_each(|c| *c += 1);
+        }
+        for _ in 0..1000 {
+            let i = rng.below(n);
+            let j = i + rng.below((n - i).min(50));
+            let k = coverage[rng.below(n)];
+            assert_eq!(is_there.is_there(i, j, k), coverage[i..=j].contains(&k));
         }
+    }
 
-        0
+    #[test]
+    fn remove_and_count_against_naive() {
+        let mut rng = Rng::new(45);
+
+        for _ in 0..300 {
+            let n = 1 + rng.below(60);
+            let mut coverage: Vec<usize> = vec![0; n];
+            let mut added: Vec<(usize, usize)> = Vec::new();
+            let mut is_there = IsThere::new(n as u128);
+
+            for _ in 0..200 {
+                let i = rng.below(n);
+                let j = i + rng.below(n - i);
+                let k = rng.below(6);
+                let expected: Vec<usize> = (i..=j).filter(|&p| coverage[p] == k).collect();
+
+                match rng.below(4) {
+                    0 => {
+                        is_there.add_segment(i, j);
+                        added.push((i, j));
+                        coverage[i..=j].iter_mut().for_each(|c| *c += 1);
+                    }
+                    1 if !added.is_empty() => {
+                        let (i, j) = added.swap_remove(rng.below(added.len()));
+                        assert!(is_there.remove_segment(i, j));
+                        coverage[i..=j].iter_mut().for_each(|c| *c -= 1);
+                    }
+                    1 => assert!(!is_there.remove_segment(i, j)),
+                    _ => {
+                        assert_eq!(is_there.count_exact(i, j, k), expected.len());
+                        assert_eq!(is_there.positions_exact(i, j, k), expected);
+                        assert_eq!(is_there.is_there(i, j, k), !expected.is_empty());
+                    }
+                }
+            }
+        }
     }
 
-    fn update(&mut self, start: usize, end: usize, node: usize) -> u128 {
-        self.update_node(node);
+    /// Runs around the coverage where the bitmask tree moves to the summary tree.
+    #[test]
+    fn past_the_bitmask_against_naive() {
+        let mut rng = Rng::new(143);
+
+        for _ in 0..50 {
+            let n = 1 + rng.below(40);
+            let base = 110 + rng.below(20);
+            let mut coverage: Vec<usize> = vec![base; n];
+            let mut added: Vec<(usize, usize)> = vec![(0, n - 1); base];
+            let mut is_there = IsThere::new(n as u128);
+            for _ in 0..base {
+                is_there.add_segment(0, n - 1);
+            }
 
-        if self.nodes[node].range.start > end || self.nodes[node].range.end < start {
-            return self.nodes[node].key;
+            for _ in 0..300 {
+                let i = rng.below(n);
+                let j = i + rng.below(n - i);
+                let k = base - 5 + rng.below(30);
+                let expected: Vec<usize> = (i..=j).filter(|&p| coverage[p] == k).collect();
+
+                match rng.below(4) {
+                    0 => {
+                        is_there.add_segment(i, j);
+                        added.push((i, j));
+                        coverage[i..=j].iter_mut().for_each(|c| *c += 1);
+                    }
+                    1 => {
+                        let (i, j) = added.swap_remove(rng.below(added.len()));
+                        assert!(is_there.remove_segment(i, j));
+                        coverage[i..=j].iter_mut().for_each(|c| *c -= 1);
+                    }
+                    _ => {
+                        assert_eq!(is_there.is_there(i, j, k), !expected.is_empty());
+                        assert_eq!(is_there.count_exact(i, j, k), expected.len());
+                        assert_eq!(is_there.positions_exact(i, j, k), expected);
+                    }
+                }
+            }
         }
+    }
+
+    #[test]
+    fn bitmask_shifts() {
+        let mut tree: LazySegTree<Or, Shift> = LazySegTree::new(&[1; 4]);
+
+        // Pending shifts compose both ways, and removing undoes adding
+        tree.range_apply(0, 3, 2);
+        tree.range_apply(1, 2, -1);
+        tree.range_apply(2, 3, 125);
+        assert_eq!(tree.range_query(0, 1), 0b110);
+        assert_eq!(tree.range_query(2, 3), 1 << 126 | 1 << 127);
+
+        tree.range_apply(0, 3, -1);
+        tree.range_apply(2, 3, -125);
+        assert_eq!(tree.range_query(0, 3), 0b11);
+    }
 
-        if self.nodes[node].range.start >= start && self.nodes[node].range.end <= end {
-            self.nodes[node].key <<= 1;
-            self.propagate(node, 1);
-            return self.nodes[node].key;
+    #[test]
+    fn remove_only_added_segments() {
+        let mut is_there = IsThere::new(10);
+
+        is_there.add_segment(2, 5);
+        is_there.add_segment(2, 5);
+        assert!(!is_there.remove_segment(2, 6));
+        assert_eq!(is_there.count_exact(0, 9, 2), 4);
+
+        assert!(is_there.remove_segment(2, 5));
+        assert_eq!(is_there.positions_exact(0, 9, 1), vec![2, 3, 4, 5]);
+        assert!(is_there.remove_segment(2, 5));
+        assert!(!is_there.remove_segment(2, 5));
+        assert_eq!(is_there.count_exact(0, 9, 0), 10);
+
+        // Clamped like in add_segment
+        is_there.add_segment(7, 20);
+        assert!(is_there.remove_segment(7, 9));
+        assert_eq!(is_there.count_exact(0, 100, 0), 10);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut is_there = IsThere::new(10);
+
+        is_there.add_segment(5, 100);
+        assert!(is_there.is_there(9, 200, 1));
+        assert!(!is_there.is_there(10, 200, 0));
+        assert!(!is_there.is_there(4, 3, 0));
+        assert_eq!(is_there.query(1, 0, 9, u128::MAX), 0);
+    }
+}
+
+#[cfg(test)]
+mod update_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn random_against_naive() {
+        let mut rng = Rng::new(49);
+
+        for _ in 0..500 {
+            let n = 1 + rng.below(40);
+            let mut naive: Vec<i32> = (0..n).map(|_| rng.key(-50..=50)).collect();
+            let mut min_max = MinMax::new(naive.clone());
+
+            for _ in 0..100 {
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+                let x = rng.key(-50..=50);
+                let slice = &mut naive[start..=end];
+
+                match rng.below(6) {
+                    0 => {
+                        min_max.chmin(start..=end, x).unwrap();
+                        slice.iter_mut().for_each(|v| *v = (*v).min(x));
+                    }
+                    1 => {
+                        min_max.add(start..=end, x).unwrap();
+                        slice.iter_mut().for_each(|v| *v += x);
+                    }
+                    2 => {
+                        min_max.assign(start..=end, x).unwrap();
+                        slice.iter_mut().for_each(|v| *v = x);
+                    }
+                    3 => {
+                        min_max.set(start, x).unwrap();
+                        slice[0] = x;
+                    }
+                    _ => {
+                        assert_eq!(min_max.max(start..=end), Ok(*slice.iter().max().unwrap()));
+                        assert_eq!(min_max.min(start..=end), Ok(*slice.iter().min().unwrap()));
+                    }
+                }
+            }
         }
+    }
+
+    #[test]
+    fn composition() {
+        let mut min_max = MinMax::new(vec![10, 20, 30, 40]);
+
+        // The updates on the whole range stay pending at the root, then get
+        // composed with the ones on the halves when pushed down
+        min_max.add(.., 5).unwrap();
+        min_max.assign(..2, 1).unwrap();
+        assert_eq!(min_max.process(&[Query::min(..), Query::max(..)]), Ok(vec![1, 45]));
+
+        min_max.assign(.., 7).unwrap();
+        min_max.chmin(.., 3).unwrap();
+        min_max.add(2.., 10).unwrap();
+        assert_eq!(min_max.process(&[Query::max(..2), Query::min(2..)]), Ok(vec![3, 13]));
+
+        min_max.chmin(.., 5).unwrap();
+        min_max.add(.., 100).unwrap();
+        min_max.chmin(1..=2, 50).unwrap();
+        min_max.add(.., -1).unwrap();
+        assert_eq!(
+            min_max.process(&[Query::set(3, 0), Query::max(0..1), Query::max(1..3), Query::min(..)]),
+            Ok(vec![102, 49, 0])
+        );
+    }
+
+    #[test]
+    fn compose_rules() {
+        let add = |add| Update::AddCap { add, cap: i64::MAX };
+        let chmin = |cap| Update::AddCap { add: 0, cap };
+
+        assert_eq!(Update::compose(&Update::Assign(3), &add(5)), Update::Assign(3));
+        assert_eq!(Update::compose(&add(5), &Update::Assign(3)), Update::Assign(8));
+        assert_eq!(Update::compose(&chmin(4), &Update::Assign(7)), Update::Assign(4));
+        assert_eq!(
+            Update::compose(&add(-2), &chmin(4)),
+            Update::AddCap { add: -2, cap: 2 }
+        );
+        assert_eq!(Update::compose(&add(-2), &add(7)), add(5));
+        assert_eq!(Update::apply(&chmin(4), &(1, 9)), (1, 4));
+    }
 
-        if self.nodes[node].range.start <= start || self.nodes[node].range.end >= end {
-            let left_overlap = self.update(start, end, self.nodes[node].id_left.unwrap());
-            let right_overlap = self.update(start, end, self.nodes[node].id_right.unwrap());
+    #[test]
+    fn overflow() {
+        let mut min_max = MinMax::new(vec![i32::MAX - 1, 0, i32::MIN + 1]);
+
+        // Nothing changes if any value of the range would overflow
+        assert_eq!(min_max.add(..2, 2), Err(RangeError::Overflow));
+        assert_eq!(min_max.add(1.., -2), Err(RangeError::Overflow));
+        assert_eq!(min_max.process(&[Query::max(..)]), Ok(vec![i32::MAX - 1]));
+        assert_eq!(min_max.min(..), Ok(i32::MIN + 1));
+
+        min_max.add(..2, 1).unwrap();
+        min_max.add(1.., -1).unwrap();
+        assert_eq!(min_max.max(..1), Ok(i32::MAX));
+        assert_eq!(min_max.min(2..), Ok(i32::MIN));
+
+        // A cap that keeps the values in range makes the add valid
+        let update = Update::AddCap { add: 10, cap: 5 };
+        assert_eq!(min_max.update(..2, update), Ok(()));
+        assert_eq!(min_max.max(..), Ok(5));
+        assert_eq!(
+            min_max.process(&[Query::add(.., i32::MAX), Query::max(..)]),
+            Err(RangeError::Overflow)
+        );
+        assert_eq!(RangeError::Overflow.to_string(), "the update takes a value out of the i32 range");
+    }
+
+    #[test]
+    fn large_pending_adds() {
+        let add = |add| Update::AddCap { add, cap: i64::MAX };
+        assert_eq!(Update::compose(&add(i64::MAX), &add(i64::MAX)), add(i64::MAX));
+        assert_eq!(Update::compose(&add(i64::MIN), &add(i64::MIN)), add(i64::MIN));
+
+        // Both adds stay pending at the root and get composed there
+        let mut min_max = MinMax::new(vec![-3, 1, 4, 1]);
+        let update = Update::AddCap { add: i64::MAX, cap: 5 };
+        min_max.update(.., update).unwrap();
+        min_max.update(.., update).unwrap();
+        assert_eq!(min_max.min(..2), Ok(5));
+
+        let update = Update::AddCap { add: i64::MIN, cap: i64::MAX };
+        assert_eq!(min_max.update(.., update), Err(RangeError::Overflow));
+        min_max.add(..=1, -7).unwrap();
+        assert_eq!(min_max.process(&[Query::min(..), Query::max(2..)]), Ok(vec![-2, 5]));
+    }
+}
 
-            self.nodes[node].key = right_overlap | left_overlap;
-            return self.nodes[node].key;
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn random_against_naive() {
+        let mut rng = Rng::new(50);
+
+        for _ in 0..500 {
+            let n = 1 + rng.below(40);
+            let mut naive: Vec<i32> = (0..n).map(|_| rng.key(-10..=10)).collect();
+            let mut min_max = MinMax::new(naive.clone());
+
+            for _ in 0..100 {
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+                let x = rng.key(-10..=10);
+
+                match rng.below(5) {
+                    0 => {
+                        min_max.chmin(start..=end, x).unwrap();
+                        naive[start..=end].iter_mut().for_each(|v| *v = (*v).min(x));
+                    }
+                    1 => {
+                        min_max.add(start..=end, x).unwrap();
+                        naive[start..=end].iter_mut().for_each(|v| *v += x);
+                    }
+                    _ => {
+                        let max = *naive[start..=end].iter().max().unwrap();
+                        let argmax = (start..=end).find(|&i| naive[i] == max).unwrap();
+                        assert_eq!(min_max.argmax(start..=end), Ok(argmax));
+
+                        let above = min_max.find_first(start, |_, max| max >= x);
+                        assert_eq!(above, (start..n).find(|&i| naive[i] >= x));
+                        let below = min_max.find_first(start, |min, _| min <= x);
+                        assert_eq!(below, (start..n).find(|&i| naive[i] <= x));
+                        let above = min_max.find_last(end, |_, max| max >= x);
+                        assert_eq!(above, (0..=end).rev().find(|&i| naive[i] >= x));
+                    }
+                }
+            }
         }
+    }
 
-        0
+    #[test]
+    fn bounds() {
+        let mut min_max = MinMax::new(vec![3, 7, 7, 1]);
+
+        assert_eq!(min_max.argmax(..), Ok(1));
+        assert_eq!(min_max.argmax(2..), Ok(2));
+        assert_eq!(min_max.argmax(3..3), Err(RangeError::Empty));
+        assert_eq!(min_max.argmax(..5), Err(RangeError::OutOfBounds { end: 5, len: 4 }));
+
+        assert_eq!(min_max.find_first(4, |_, _| true), None);
+        assert_eq!(min_max.find_first(0, |_, max| max >= 8), None);
+        assert_eq!(min_max.find_last(100, |_, _| true), Some(3));
+        assert_eq!(min_max.find_last(0, |min, _| min <= 1), None);
     }
 }
@@ -116,47 +116,50 @@ fn file_reader_p1(input_path: String, output_path: String) -> io::Result<()> {
             .filter_map(|s| s.parse().ok())
             .collect();
 
-        let result: Option<i32> = if numbers.len() == 3 {
-            min_max.query(
-                numbers[0] as usize,
-                numbers[1] as usize,
-                numbers[2] as usize,
-                0,
-            )
-        } else {
-            min_max.query(
-                numbers[0] as usize,
-                numbers[1] as usize,
-                numbers[2] as usize,
-                numbers[3],
+        // The testset positions are 1-based
+        let range = zero_based(numbers[1])?..=zero_based(numbers[2])?;
+        if numbers[0] == 0 {
+            min_max.chmin(range, numbers[3]).map_err(Error::other)?;
+            continue;
+        }
+        let result: Option<i32> = min_max.max(range).ok();
+
+        let mut output = String::new();
+        let _ = output_reader.read_line(&mut output);
+        let success: i32 = output.trim().parse().ok().unwrap();
+
+        if success != result.unwrap() {
+            wrong += 1;
+            println!(
+                "max   : {:?} ## range: {} - {}     ######### ( {} ) ######## -> {}",
+                result,
+                numbers[1],
+                numbers[2],
+                (success == result.unwrap()),
+                success
             )
-        };
-
-        if numbers.len() == 3 {
-            let mut output = String::new();
-            let _ = output_reader.read_line(&mut output);
-            let success: i32 = output.trim().parse().ok().unwrap();
-
-            if success != result.unwrap() {
-                wrong += 1;
-                println!(
-                    "max   : {:?} ## range: {} - {}     ######### ( {} ) ######## -> {}",
-                    result,
-                    numbers[1],
-                    numbers[2],
-                    (success == result.unwrap()),
-                    success
-                )
-            }
         }
     }
 
     if wrong > 0 {
-        return Err(Error::new(io::ErrorKind::Other, "Test case failed"));
+        return Err(Error::other("Test case failed"));
     }
     Ok(())
 }
 
+/// Converts a 1-based position of the testset into a 0-based one.
+fn zero_based(position: i32) -> io::Result<usize> {
+    usize::try_from(position)
+        .ok()
+        .and_then(|position| position.checked_sub(1))
+        .ok_or_else(|| {
+            Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid 1-based position: {}", position),
+            )
+        })
+}
+
 fn file_reader_p2(input_path: String, output_path: String) -> io::Result<()> {
     if !Path::new(&input_path).exists() && !Path::new(&output_path).exists() {
         println!("File {} not found", input_path);
@@ -192,34 +195,28 @@ fn file_reader_p2(input_path: String, output_path: String) -> io::Result<()> {
             .filter_map(|s| s.parse().ok())
             .collect();
 
-        let result: i8 = if numbers.len() == 2 {
-            is_there.query(0, numbers[0] as usize, numbers[1] as usize, 0)
-        } else {
-            is_there.query(
-                1,
-                numbers[0] as usize,
-                numbers[1] as usize,
-                numbers[2] as u128,
+        if numbers.len() == 2 {
+            is_there.add_segment(numbers[0] as usize, numbers[1] as usize);
+            continue;
+        }
+        let (i, j, k) = (numbers[0] as usize, numbers[1] as usize, numbers[2] as usize);
+        let result: i8 = is_there.is_there(i, j, k) as i8;
+
+        let mut output = String::new();
+        let _ = output_reader.read_line(&mut output);
+        let success: i8 = output.trim().parse().ok().unwrap();
+
+        if success != result {
+            wrong += 1;
+            println!(
+                "max   : {:?} ## range: {} - {}     ######### ( FALSE ) ######## -> {}",
+                result, numbers[0], numbers[1], success
             )
-        };
-
-        if numbers.len() == 3 {
-            let mut output = String::new();
-            let _ = output_reader.read_line(&mut output);
-            let success: i8 = output.trim().parse().ok().unwrap();
-
-            if success != result {
-                wrong += 1;
-                println!(
-                    "max   : {:?} ## range: {} - {}     ######### ( FALSE ) ######## -> {}",
-                    result, numbers[0], numbers[1], success
-                )
-            }
         }
     }
 
     if wrong > 0 {
-        return Err(Error::new(io::ErrorKind::Other, "Test case failed"));
+        return Err(Error::other("Test case failed"));
     }
 
     Ok(())
@@ -0,0 +1,302 @@
+use crate::{inclusive_bounds, RangeError};
+use std::ops::RangeBounds;
+
+/// A handle to a version of a `PersistentMinMax`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Version(usize);
+
+/// A node of the shared pool. Children are pool indices, and the range of a node
+/// is computed while walking down from the root.
+struct PoolNode {
+    /// Maximum of the range, with `tag` already applied
+    max: i32,
+    /// Chmin applied to the whole range, `i32::MAX` if none. It's never pushed down,
+    /// so the values of a range are capped by the tags of all its ancestors.
+    tag: i32,
+    id_left: Option<usize>,
+    id_right: Option<usize>,
+}
+
+/// `MinMax` that keeps all its versions, using path copying.
+///
+/// `chmin` never modifies a version: it copies the O(log n) nodes whose range is
+/// partially covered, and returns a new version that shares every other node with
+/// the version it comes from. The tags stay in the nodes where they're applied
+/// instead of being pushed down, so queries never copy nodes either.
+/// With m updates the pool has O(n + m log n) nodes.
+/// Positions are 0-based.
+pub struct PersistentMinMax {
+    pool: Vec<PoolNode>,
+    /// Pool index of the root of every version
+    versions: Vec<usize>,
+    len: usize,
+}
+
+impl PersistentMinMax {
+    /// Builds the first version over `arr`, in O(n).
+    ///
+    /// # Panics
+    /// Panics if `arr` is empty.
+    pub fn new(arr: &[i32]) -> Self {
+        assert!(!arr.is_empty(), "The segment tree needs at least one value");
+
+        let mut tree = Self {
+            pool: Vec::with_capacity(2 * arr.len() - 1),
+            versions: Vec::new(),
+            len: arr.len(),
+        };
+        let root = tree.build(arr);
+        tree.versions.push(root);
+
+        tree
+    }
+
+    /// Returns the first version, built over the initial values.
+    pub fn first(&self) -> Version {
+        Version(0)
+    }
+
+    /// Returns the last version created. The versions are numbered in order of
+    /// creation, so `Version(v)` is the one made by the v-th update.
+    pub fn latest(&self) -> Version {
+        Version(self.versions.len() - 1)
+    }
+
+    /// Returns the number of versions created so far.
+    pub fn versions(&self) -> usize {
+        self.versions.len()
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`, since the tree can't be built over no values.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns the version made by the `v`-th update, `v == 0` being the first one,
+    /// or `None` if there are not so many updates.
+    pub fn version(&self, v: usize) -> Option<Version> {
+        (v < self.versions.len()).then_some(Version(v))
+    }
+
+    /// Sets every value `x` in `range` of `version` to `min(x, t)`, and returns
+    /// the new version. O(log n).
+    pub fn chmin(
+        &mut self,
+        version: Version,
+        range: impl RangeBounds<usize>,
+        t: i32,
+    ) -> Result<Version, RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len)?;
+
+        let root = self.chmin_node(start, end, t, self.versions[version.0], 0, self.len - 1);
+        self.versions.push(root);
+
+        Ok(self.latest())
+    }
+
+    /// Returns the maximum of the values in `range` of `version`. O(log n).
+    pub fn max(
+        &self,
+        version: Version,
+        range: impl RangeBounds<usize>,
+    ) -> Result<i32, RangeError> {
+        let (start, end) = inclusive_bounds(&range, self.len)?;
+
+        Ok(self.max_node(start, end, self.versions[version.0], 0, self.len - 1, i32::MAX))
+    }
+
+    /// Builds the subtree over `arr` and returns its root.
+    fn build(&mut self, arr: &[i32]) -> usize {
+        if arr.len() == 1 {
+            return self.push_node(arr[0], i32::MAX, None, None);
+        }
+
+        let mid = (arr.len() - 1) / 2;
+        let left = self.build(&arr[..=mid]);
+        let right = self.build(&arr[mid + 1..]);
+
+        let max = self.pool[left].max.max(self.pool[right].max);
+        self.push_node(max, i32::MAX, Some(left), Some(right))
+    }
+
+    /// `node` covers `[lo, hi]` and the tags of its ancestors cap it to `cap`.
+    fn max_node(
+        &self,
+        start: usize,
+        end: usize,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        cap: i32,
+    ) -> i32 {
+        let node = &self.pool[node];
+
+        // No overlap
+        if lo > end || hi < start {
+            return i32::MIN;
+        }
+
+        // Full overlap
+        if lo >= start && hi <= end {
+            return node.max.min(cap);
+        }
+
+        // Partial overlap
+        let cap = cap.min(node.tag);
+        let mid = lo + (hi - lo) / 2;
+        let left = self.max_node(start, end, node.id_left.unwrap(), lo, mid, cap);
+        let right = self.max_node(start, end, node.id_right.unwrap(), mid + 1, hi, cap);
+
+        left.max(right)
+    }
+
+    /// Returns the copy of `node`, which covers `[lo, hi]`, with the chmin applied.
+    /// Nodes out of the range or with nothing above `t` are shared, not copied.
+    fn chmin_node(
+        &mut self,
+        start: usize,
+        end: usize,
+        t: i32,
+        node: usize,
+        lo: usize,
+        hi: usize,
+    ) -> usize {
+        let PoolNode {
+            max,
+            tag,
+            id_left,
+            id_right,
+        } = self.pool[node];
+
+        // No overlap, or nothing to change
+        if lo > end || hi < start || max <= t {
+            return node;
+        }
+
+        // Full overlap
+        if lo >= start && hi <= end {
+            return self.push_node(t, tag.min(t), id_left, id_right);
+        }
+
+        // Partial overlap
+        let mid = lo + (hi - lo) / 2;
+        let left = self.chmin_node(start, end, t, id_left.unwrap(), lo, mid);
+        let right = self.chmin_node(start, end, t, id_right.unwrap(), mid + 1, hi);
+
+        let max = self.pool[left].max.max(self.pool[right].max).min(tag);
+        self.push_node(max, tag, Some(left), Some(right))
+    }
+
+    fn push_node(
+        &mut self,
+        max: i32,
+        tag: i32,
+        id_left: Option<usize>,
+        id_right: Option<usize>,
+    ) -> usize {
+        self.pool.push(PoolNode {
+            max,
+            tag,
+            id_left,
+            id_right,
+        });
+
+        self.pool.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod persistent_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn every_version_against_replay() {
+        let mut rng = Rng::new(47);
+
+        for _ in 0..100 {
+            let n = 1 + rng.below(30);
+            let initial: Vec<i32> = (0..n).map(|_| rng.key(-50..=50)).collect();
+            let mut tree = PersistentMinMax::new(&initial);
+
+            // Every update starts from a random past version
+            let mut updates: Vec<(usize, usize, usize, i32)> = Vec::new();
+            for _ in 0..60 {
+                let base = rng.below(tree.versions());
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+                let t = rng.key(-50..=50);
+
+                let version = tree.chmin(tree.version(base).unwrap(), start..=end, t).unwrap();
+                assert_eq!(version, tree.latest());
+                updates.push((base, start, end, t));
+            }
+            assert_eq!(tree.versions(), updates.len() + 1);
+
+            // Replays the updates from scratch for every version
+            let replay = |v: usize| {
+                let mut chain: Vec<usize> = Vec::new();
+                let mut current = v;
+                while current > 0 {
+                    chain.push(current);
+                    current = updates[current - 1].0;
+                }
+
+                let mut values = initial.clone();
+                for &v in chain.iter().rev() {
+                    let (_, start, end, t) = updates[v - 1];
+                    values[start..=end].iter_mut().for_each(|x| *x = (*x).min(t));
+                }
+                values
+            };
+
+            for v in 0..tree.versions() {
+                let values = replay(v);
+                let version = tree.version(v).unwrap();
+
+                for start in 0..n {
+                    for end in start..n {
+                        let expected = *values[start..=end].iter().max().unwrap();
+                        assert_eq!(tree.max(version, start..=end), Ok(expected));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn memory() {
+        let n = 1 << 12;
+        let mut rng = Rng::new(48);
+        let mut tree = PersistentMinMax::new(&vec![1000; n]);
+
+        let m = 1000;
+        for _ in 0..m {
+            let start = rng.below(n);
+            let end = start + rng.below(n - start);
+            tree.chmin(tree.latest(), start..=end, rng.key(0..=999)).unwrap();
+        }
+
+        // At most two copied paths of 12 levels, plus the root, per update
+        assert!(tree.pool.len() <= 2 * n - 1 + m * (4 * 12 + 1));
+        assert_eq!(tree.max(tree.first(), ..), Ok(1000));
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        let mut tree = PersistentMinMax::new(&[3, 1, 2]);
+
+        assert_eq!(
+            tree.chmin(tree.first(), 0..4, 0),
+            Err(RangeError::OutOfBounds { end: 4, len: 3 })
+        );
+        assert_eq!(tree.versions(), 1);
+        assert_eq!(tree.max(tree.first(), 1..1), Err(RangeError::Empty));
+        assert!(tree.version(1).is_none());
+    }
+}
@@ -0,0 +1,55 @@
+//! Seeded pseudo-random numbers for the randomized tests, so that the same
+//! seed always produces the same sequence of operations.
+//!
+//! It mirrors the `Rng` of `hands_on_1::random`, trimmed to what the tests here
+//! use, so that the two crates stay independent.
+
+use std::ops::RangeInclusive;
+
+/// A small pseudo-random number generator (SplitMix64).
+/// It is not suitable for cryptography, only for generating test inputs.
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next pseudo-random 64 bits value.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..bound`.
+    ///
+    /// # Panics
+    /// Panics if `bound` is 0.
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "The bound must be positive");
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns a value in the inclusive range `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is empty.
+    pub(crate) fn key(&mut self, range: RangeInclusive<i32>) -> i32 {
+        let (low, high) = (*range.start() as i64, *range.end() as i64);
+        assert!(low <= high, "The range of keys is empty");
+
+        let width = (high - low + 1) as u64;
+        (low + (self.next_u64() % width) as i64) as i32
+    }
+
+    /// Returns `true` or `false` with the same probability.
+    pub(crate) fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}
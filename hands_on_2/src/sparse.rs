@@ -0,0 +1,315 @@
+use crate::RangeError;
+use std::ops::{Bound, RangeBounds};
+
+struct SparseNode {
+    /// Maximum of the range, with the tags of the ancestors already applied
+    max: i32,
+    /// Chmin still to be pushed down to the children
+    lazy: Option<i32>,
+    id_left: Option<usize>,
+    id_right: Option<usize>,
+}
+
+impl SparseNode {
+    fn new(max: i32) -> Self {
+        Self {
+            max,
+            lazy: None,
+            id_left: None,
+            id_right: None,
+        }
+    }
+}
+
+/// Range chmin and range max over a sparse array with an index for every `u64`,
+/// where untouched positions have a default value.
+///
+/// It's a dynamic segment tree: a node without children has the same value in all
+/// its positions, so children are allocated only when a range splits it.
+/// Every operation allocates O(64) nodes at most and takes O(64) time.
+/// Nodes don't store their range, which is computed while walking down from the root.
+pub struct SparseMinMax {
+    nodes: Vec<SparseNode>,
+}
+
+impl SparseMinMax {
+    /// Builds the array with all the positions set to `default`.
+    pub fn new(default: i32) -> Self {
+        Self {
+            nodes: vec![SparseNode::new(default)],
+        }
+    }
+
+    /// Returns the number of allocated nodes.
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the value at `index`.
+    pub fn get(&mut self, index: u64) -> i32 {
+        self.max_node(index, index, 0, 0, u64::MAX)
+    }
+
+    /// Sets the value at `index` to `value`.
+    pub fn set(&mut self, index: u64, value: i32) {
+        self.set_node(index, value, 0, 0, u64::MAX);
+    }
+
+    /// Sets every value `x` in `range` to `min(x, t)`.
+    pub fn chmin(&mut self, range: impl RangeBounds<u64>, t: i32) -> Result<(), RangeError> {
+        let (start, end) = inclusive_bounds(&range)?;
+        self.chmin_node(start, end, t, 0, 0, u64::MAX);
+
+        Ok(())
+    }
+
+    /// Returns the maximum of the values in `range`.
+    pub fn max(&mut self, range: impl RangeBounds<u64>) -> Result<i32, RangeError> {
+        let (start, end) = inclusive_bounds(&range)?;
+
+        Ok(self.max_node(start, end, 0, 0, u64::MAX))
+    }
+
+    /// `node` covers `[lo, hi]`, which overlaps `[start, end]`.
+    fn max_node(&mut self, start: u64, end: u64, node: usize, lo: u64, hi: u64) -> i32 {
+        // Full overlap, or the same value everywhere
+        if (start <= lo && hi <= end) || self.nodes[node].id_left.is_none() {
+            return self.nodes[node].max;
+        }
+
+        self.push(node);
+        let (left_id, right_id) = self.children(node);
+        let mid = lo + (hi - lo) / 2;
+
+        let mut max = i32::MIN;
+        if start <= mid {
+            max = max.max(self.max_node(start, end, left_id, lo, mid));
+        }
+        if end > mid {
+            max = max.max(self.max_node(start, end, right_id, mid + 1, hi));
+        }
+
+        max
+    }
+
+    /// `node` covers `[lo, hi]`, which overlaps `[start, end]`.
+    fn chmin_node(&mut self, start: u64, end: u64, t: i32, node: usize, lo: u64, hi: u64) {
+        if self.nodes[node].max <= t {
+            return;
+        }
+
+        // Full overlap
+        if start <= lo && hi <= end {
+            self.apply(node, t);
+            return;
+        }
+
+        // Partial overlap
+        self.push(node);
+        let (left_id, right_id) = self.children(node);
+        let mid = lo + (hi - lo) / 2;
+
+        if start <= mid {
+            self.chmin_node(start, end, t, left_id, lo, mid);
+        }
+        if end > mid {
+            self.chmin_node(start, end, t, right_id, mid + 1, hi);
+        }
+
+        self.nodes[node].max = self.nodes[left_id].max.max(self.nodes[right_id].max);
+    }
+
+    /// `node` covers `[lo, hi]`, which contains `index`.
+    fn set_node(&mut self, index: u64, value: i32, node: usize, lo: u64, hi: u64) {
+        if lo == hi {
+            self.nodes[node].max = value;
+            return;
+        }
+
+        self.push(node);
+        let (left_id, right_id) = self.children(node);
+        let mid = lo + (hi - lo) / 2;
+
+        if index <= mid {
+            self.set_node(index, value, left_id, lo, mid);
+        } else {
+            self.set_node(index, value, right_id, mid + 1, hi);
+        }
+
+        self.nodes[node].max = self.nodes[left_id].max.max(self.nodes[right_id].max);
+    }
+
+    fn apply(&mut self, node: usize, t: i32) {
+        let node = &mut self.nodes[node];
+
+        if node.max > t {
+            node.max = t;
+            // A node without children is all at its maximum, no tag needed
+            if node.id_left.is_some() {
+                node.lazy = Some(node.lazy.map_or(t, |lazy| lazy.min(t)));
+            }
+        }
+    }
+
+    /// Pushes the pending tag of `node` to its children, allocating them if they
+    /// are missing: they take the value of `node`, which is the same everywhere.
+    fn push(&mut self, node: usize) {
+        if self.nodes[node].id_left.is_none() {
+            let max = self.nodes[node].max;
+
+            self.nodes[node].id_left = Some(self.nodes.len());
+            self.nodes.push(SparseNode::new(max));
+            self.nodes[node].id_right = Some(self.nodes.len());
+            self.nodes.push(SparseNode::new(max));
+        }
+
+        if let Some(t) = self.nodes[node].lazy.take() {
+            let (left_id, right_id) = self.children(node);
+
+            self.apply(left_id, t);
+            self.apply(right_id, t);
+        }
+    }
+
+    fn children(&self, node: usize) -> (usize, usize) {
+        (self.nodes[node].id_left.unwrap(), self.nodes[node].id_right.unwrap())
+    }
+}
+
+/// Converts `range` into inclusive bounds, any non-empty range of `u64` being valid.
+fn inclusive_bounds(range: &impl RangeBounds<u64>) -> Result<(u64, u64), RangeError> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => Some(start),
+        Bound::Excluded(&start) => start.checked_add(1),
+        Bound::Unbounded => Some(0),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => Some(end),
+        Bound::Excluded(&end) => end.checked_sub(1),
+        Bound::Unbounded => Some(u64::MAX),
+    };
+
+    match (start, end) {
+        (Some(start), Some(end)) if start <= end => Ok((start, end)),
+        _ => Err(RangeError::Empty),
+    }
+}
+
+#[cfg(test)]
+mod sparse_tests {
+    use super::*;
+    use crate::random::Rng;
+
+    /// Splits the positions in blocks of `1 << 40` and only sets the first position
+    /// of a block, so a block is exactly the value of that position and the value
+    /// of all the others, which are always equal.
+    #[test]
+    fn random_blocks_against_naive() {
+        let mut rng = Rng::new(46);
+        let stride: u64 = 1 << 40;
+
+        for _ in 0..200 {
+            let n = 1 + rng.below(40);
+            let default = rng.key(-50..=50);
+            // (first position, all the others) of every block
+            let mut naive: Vec<(i32, i32)> = vec![(default, default); n];
+            let mut sparse = SparseMinMax::new(default);
+
+            for _ in 0..100 {
+                let start = rng.below(n);
+                let end = start + rng.below(n - start);
+                let range = start as u64 * stride..(end as u64 + 1) * stride;
+
+                match rng.below(3) {
+                    0 => {
+                        let value = rng.key(-50..=50);
+                        sparse.set(start as u64 * stride, value);
+                        naive[start].0 = value;
+                    }
+                    1 => {
+                        let t = rng.key(-50..=50);
+                        sparse.chmin(range, t).unwrap();
+                        for block in &mut naive[start..=end] {
+                            *block = (block.0.min(t), block.1.min(t));
+                        }
+                    }
+                    _ => {
+                        let expected = naive[start..=end].iter().map(|&(a, b)| a.max(b)).max();
+                        assert_eq!(sparse.max(range).ok(), expected);
+                        assert_eq!(sparse.get(start as u64 * stride), naive[start].0);
+                        assert_eq!(sparse.get(start as u64 * stride + 1), naive[start].1);
+                    }
+                }
+            }
+
+            // At most two paths from the root are split by an operation
+            assert!(sparse.nodes() <= 1 + 100 * 4 * 64);
+        }
+    }
+
+    #[test]
+    fn dense_prefix_against_naive() {
+        let mut rng = Rng::new(47);
+
+        for _ in 0..200 {
+            let n = 1 + rng.below(40) as u64;
+            let default = rng.key(-50..=50);
+            let mut naive: Vec<i32> = vec![default; n as usize];
+            let mut sparse = SparseMinMax::new(default);
+            // Positions from n on are never set nor changed, except by chmin over all of them
+            let mut tail = default;
+
+            for _ in 0..100 {
+                let start = rng.below(n as usize) as u64;
+                let end = start + rng.below((n - start) as usize) as u64;
+
+                match rng.below(4) {
+                    0 => {
+                        let value = rng.key(-50..=50);
+                        sparse.set(start, value);
+                        naive[start as usize] = value;
+                    }
+                    1 => {
+                        let t = rng.key(-50..=50);
+                        sparse.chmin(start..=end, t).unwrap();
+                        naive[start as usize..=end as usize].iter_mut().for_each(|x| *x = (*x).min(t));
+                    }
+                    2 => {
+                        let t = rng.key(-50..=50);
+                        sparse.chmin(start.., t).unwrap();
+                        naive[start as usize..].iter_mut().for_each(|x| *x = (*x).min(t));
+                        tail = tail.min(t);
+                    }
+                    _ => {
+                        let expected = naive[start as usize..=end as usize].iter().max().copied();
+                        assert_eq!(sparse.max(start..=end).ok(), expected);
+
+                        let expected = naive[start as usize..].iter().copied().max().unwrap().max(tail);
+                        assert_eq!(sparse.max(start..), Ok(expected));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn allocates_on_demand() {
+        let mut sparse = SparseMinMax::new(7);
+
+        assert_eq!(sparse.max(..), Ok(7));
+        sparse.chmin(.., 5).unwrap();
+        assert_eq!(sparse.nodes(), 1);
+
+        sparse.set(10u64.pow(18), 100);
+        assert_eq!(sparse.nodes(), 1 + 2 * 64);
+        assert_eq!(sparse.max(..), Ok(100));
+        assert_eq!(sparse.max(..10u64.pow(18)), Ok(5));
+        assert_eq!(sparse.get(u64::MAX), 5);
+
+        sparse.chmin(10u64.pow(18).., 50).unwrap();
+        assert_eq!(sparse.get(10u64.pow(18)), 50);
+        assert_eq!(sparse.max(5..5), Err(RangeError::Empty));
+        assert_eq!(sparse.max(..0), Err(RangeError::Empty));
+        assert_eq!(sparse.max(u64::MAX..), Ok(5));
+    }
+}